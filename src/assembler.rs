@@ -0,0 +1,955 @@
+use custom_error::custom_error;
+use std::collections::HashMap;
+use std::num::IntErrorKind;
+
+use crate::state::CellWidth;
+use crate::token::Span;
+
+custom_error! { pub MacroError
+    UnexpectedCharacter{character: char, span: Span} =
+        @{ format!("unexpected character '{}' at line {}, column {}", character, span.line, span.column) },
+    UnterminatedString{span: Span} =
+        @{ format!("unterminated string at line {}, column {}", span.line, span.column) },
+    InvalidNumber{text: String, span: Span} =
+        @{ format!("invalid number '{}' at line {}, column {}", text, span.line, span.column) },
+    NumberOutOfRange{text: String, max: u64, span: Span} =
+        @{ format!("number '{}' is out of range (at most {}) at line {}, column {}", text, max, span.line, span.column) },
+    Expected{expected: String, found: String, span: Span} =
+        @{ format!("expected {} but found {} at line {}, column {}", expected, found, span.line, span.column) },
+    UnknownStatement{name: String, span: Span} =
+        @{ format!("unknown statement or macro '{}' at line {}, column {}", name, span.line, span.column) },
+    UnknownVariable{name: String, span: Span} =
+        @{ format!("unknown variable '{}' at line {}, column {}", name, span.line, span.column) },
+    DuplicateName{name: String, span: Span} =
+        @{ format!("'{}' is already defined at line {}, column {}", name, span.line, span.column) },
+    NotAtTopLevel{span: Span} =
+        @{ format!("variables and macros must be declared outside of blocks at line {}, column {}", span.line, span.column) },
+    RecursiveMacro{name: String, span: Span} =
+        @{ format!("macro '{}' calls itself at line {}, column {}", name, span.line, span.column) }
+}
+
+impl MacroError {
+    /// Returns the location of the error in the macro source
+    pub fn span(&self) -> Span {
+        match self {
+            MacroError::UnexpectedCharacter { span, .. }
+            | MacroError::UnterminatedString { span }
+            | MacroError::InvalidNumber { span, .. }
+            | MacroError::NumberOutOfRange { span, .. }
+            | MacroError::Expected { span, .. }
+            | MacroError::UnknownStatement { span, .. }
+            | MacroError::UnknownVariable { span, .. }
+            | MacroError::DuplicateName { span, .. }
+            | MacroError::NotAtTopLevel { span }
+            | MacroError::RecursiveMacro { span, .. } => *span,
+        }
+    }
+}
+
+/// Largest magnitude of a number for any cell width, the maximum value of 16-bit cells
+///
+/// Numbers are compiled to a `+` or `-` per unit, so larger ones would produce huge programs.
+const MAX_NUMBER: u64 = u16::MAX as u64;
+
+/// Statements with a fixed number of operands and their number of operands
+const BUILTINS: [(&str, usize); 8] = [
+    ("add", 2),
+    ("sub", 2),
+    ("set", 2),
+    ("copy", 2),
+    ("out", 1),
+    ("in", 1),
+    ("while", 1),
+    ("if", 1),
+];
+
+/// Words that can't be used as names of variables or macros
+const KEYWORDS: [&str; 11] = [
+    "var", "macro", "print", "add", "sub", "set", "copy", "out", "in", "while", "if",
+];
+
+/// Brainfuck compiled from the macro language
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assembly {
+    /// The brainfuck program, it contains no characters besides commands
+    pub code: String,
+    /// Location in the macro source of the statement each character of code was compiled from
+    pub source_map: Vec<Span>,
+}
+
+/// Compiles a program in the macro language to brainfuck
+///
+/// A program declares variables, which are assigned to cells from cell 0 on, and works on them
+/// with statements:
+///
+/// ```text
+/// var a, b             # declares variables
+/// set a, 5             # sets a variable to a number
+/// add a, 3             # adds a number, sub subtracts one
+/// copy a, b            # sets b to the value of a
+/// while a { sub a, 1 } # repeats the block until a is zero
+/// if b { out b }       # runs the block once if b isn't zero
+/// in a                 # reads a byte into a
+/// print "hi\n"         # prints a string
+/// macro double x, t { copy x, t  while t { add x, 1  sub t, 1 } }
+/// double a, b          # expands a macro
+/// ```
+///
+/// Operands are separated by commas. Temporary cells for `copy`, `if`, and `print` are placed
+/// right of the variables.
+///
+/// # Arguments
+///
+/// * `source` - The program in the macro language
+/// * `cell_width` - Number of bits of the cells the program runs on, numbers must fit into a cell
+pub fn compile_macros(source: &str, cell_width: CellWidth) -> Result<Assembly, MacroError> {
+    let max_number = (cell_width.max_value() as u64).min(MAX_NUMBER);
+    let lexemes = tokenize(source, max_number)?;
+    let mut parser = Parser {
+        lexemes,
+        position: 0,
+        arities: HashMap::new(),
+    };
+    let statements = parser.block(true)?;
+
+    let mut compiler = Compiler {
+        code: String::new(),
+        source_map: Vec::new(),
+        pointer: 0,
+        variables: HashMap::new(),
+        macros: HashMap::new(),
+        next_temporary: 0,
+        expanding: Vec::new(),
+    };
+    compiler.declare(&statements)?;
+    compiler.block(&statements, &HashMap::new())?;
+
+    Ok(Assembly {
+        code: compiler.code,
+        source_map: compiler.source_map,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    Word(String),
+    Number(i64),
+    Text(Vec<u8>),
+    Comma,
+    OpenBrace,
+    CloseBrace,
+    End,
+}
+
+impl Kind {
+    fn describe(&self) -> String {
+        match self {
+            Kind::Word(word) => format!("'{}'", word),
+            Kind::Number(number) => format!("'{}'", number),
+            Kind::Text(_) => String::from("a string"),
+            Kind::Comma => String::from("','"),
+            Kind::OpenBrace => String::from("'{'"),
+            Kind::CloseBrace => String::from("'}'"),
+            Kind::End => String::from("the end of the program"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Lexeme {
+    kind: Kind,
+    span: Span,
+}
+
+/// Characters of the macro source with the line and column of the next one
+struct Scanner<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    line: usize,
+    column: usize,
+}
+
+impl Scanner<'_> {
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().map(|&(_, c)| c)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let (_, c) = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    /// Consumes the characters that match and returns them
+    fn take_while(&mut self, matches: impl Fn(&str, char) -> bool) -> String {
+        let mut taken = String::new();
+        while let Some(c) = self.peek().filter(|&c| matches(&taken, c)) {
+            taken.push(c);
+            self.next();
+        }
+        taken
+    }
+}
+
+/// Splits the macro source into words, numbers, strings, and punctuation, skipping `#` comments
+///
+/// Numbers whose magnitude exceeds max_number are rejected.
+fn tokenize(source: &str, max_number: u64) -> Result<Vec<Lexeme>, MacroError> {
+    let mut lexemes = Vec::new();
+    let mut scanner = Scanner {
+        chars: source.char_indices().peekable(),
+        line: 1,
+        column: 1,
+    };
+
+    while let Some(&(start, c)) = scanner.chars.peek() {
+        let span = Span {
+            start,
+            end: start + c.len_utf8(),
+            line: scanner.line,
+            column: scanner.column,
+            file: 0,
+        };
+
+        let kind = match c {
+            _ if c.is_whitespace() => {
+                scanner.next();
+                continue;
+            }
+            '#' => {
+                while scanner.next().is_some_and(|c| c != '\n') {}
+                continue;
+            }
+            ',' | '{' | '}' => {
+                scanner.next();
+                match c {
+                    ',' => Kind::Comma,
+                    '{' => Kind::OpenBrace,
+                    _ => Kind::CloseBrace,
+                }
+            }
+            '"' => {
+                scanner.next();
+                let mut text = String::new();
+                loop {
+                    match scanner.next() {
+                        Some('"') => break,
+                        Some('\\') => match scanner.next() {
+                            Some('n') => text.push('\n'),
+                            Some('t') => text.push('\t'),
+                            Some('0') => text.push('\0'),
+                            Some(c) => text.push(c),
+                            None => return Err(MacroError::UnterminatedString { span }),
+                        },
+                        Some(c) => text.push(c),
+                        None => return Err(MacroError::UnterminatedString { span }),
+                    }
+                }
+                Kind::Text(text.into_bytes())
+            }
+            _ if c == '-' || c.is_ascii_digit() => {
+                let text = scanner.take_while(|taken, c| {
+                    c.is_ascii_alphanumeric() || (c == '-' && taken.is_empty())
+                });
+                let span = Span {
+                    end: start + text.len(),
+                    ..span
+                };
+                let out_of_range = MacroError::NumberOutOfRange {
+                    text: text.clone(),
+                    max: max_number,
+                    span,
+                };
+                match text.parse::<i64>() {
+                    Ok(number) if number.unsigned_abs() <= max_number => Kind::Number(number),
+                    Ok(_) => return Err(out_of_range),
+                    Err(e)
+                        if matches!(
+                            e.kind(),
+                            IntErrorKind::PosOverflow | IntErrorKind::NegOverflow
+                        ) =>
+                    {
+                        return Err(out_of_range)
+                    }
+                    Err(_) => return Err(MacroError::InvalidNumber { text, span }),
+                }
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                Kind::Word(scanner.take_while(|_, c| c.is_alphanumeric() || c == '_'))
+            }
+            _ => return Err(MacroError::UnexpectedCharacter { character: c, span }),
+        };
+
+        let end = scanner
+            .chars
+            .peek()
+            .map_or(source.len(), |&(offset, _)| offset);
+        lexemes.push(Lexeme {
+            kind,
+            span: Span { end, ..span },
+        });
+    }
+
+    lexemes.push(Lexeme {
+        kind: Kind::End,
+        span: Span {
+            start: source.len(),
+            end: source.len(),
+            line: scanner.line,
+            column: scanner.column,
+            file: 0,
+        },
+    });
+    Ok(lexemes)
+}
+
+/// A variable name or a number given to a statement
+#[derive(Debug, Clone)]
+enum Operand {
+    Name(String, Span),
+    Number(i64, Span),
+}
+
+#[derive(Debug)]
+enum Statement {
+    Var {
+        names: Vec<(String, Span)>,
+    },
+    Macro {
+        name: String,
+        parameters: Vec<String>,
+        body: Vec<Statement>,
+    },
+    Print {
+        text: Vec<u8>,
+        span: Span,
+    },
+    /// A builtin statement or a macro call
+    Call {
+        name: String,
+        operands: Vec<Operand>,
+        body: Vec<Statement>,
+        span: Span,
+        /// Location of the '}' closing the body
+        end: Span,
+    },
+}
+
+struct Parser {
+    lexemes: Vec<Lexeme>,
+    position: usize,
+    /// Number of operands of the macros defined so far
+    arities: HashMap<String, usize>,
+}
+
+impl Parser {
+    fn peek(&self) -> &Lexeme {
+        &self.lexemes[self.position]
+    }
+
+    fn advance(&mut self) -> Lexeme {
+        let lexeme = self.lexemes[self.position].clone();
+        if lexeme.kind != Kind::End {
+            self.position += 1;
+        }
+        lexeme
+    }
+
+    fn expected(&self, expected: &str) -> MacroError {
+        let lexeme = self.peek();
+        MacroError::Expected {
+            expected: expected.to_string(),
+            found: lexeme.kind.describe(),
+            span: lexeme.span,
+        }
+    }
+
+    fn expect(&mut self, kind: Kind) -> Result<Span, MacroError> {
+        if self.peek().kind == kind {
+            Ok(self.advance().span)
+        } else {
+            Err(self.expected(&kind.describe()))
+        }
+    }
+
+    /// Parses a name that isn't a keyword
+    fn name(&mut self) -> Result<(String, Span), MacroError> {
+        match &self.peek().kind {
+            Kind::Word(word) if !KEYWORDS.contains(&word.as_str()) => {
+                let word = word.clone();
+                Ok((word, self.advance().span))
+            }
+            _ => Err(self.expected("a name")),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, MacroError> {
+        match self.peek().kind {
+            Kind::Number(number) => Ok(Operand::Number(number, self.advance().span)),
+            _ => self
+                .name()
+                .map(|(name, span)| Operand::Name(name, span))
+                .map_err(|_| self.expected("a variable or a number")),
+        }
+    }
+
+    /// Parses statements until the end of the program, or until '}' if the block is nested
+    fn block(&mut self, top_level: bool) -> Result<Vec<Statement>, MacroError> {
+        let mut statements = Vec::new();
+        loop {
+            match self.peek().kind {
+                Kind::End if top_level => return Ok(statements),
+                Kind::CloseBrace if !top_level => return Ok(statements),
+                _ => statements.push(self.statement(top_level)?),
+            }
+        }
+    }
+
+    /// Parses '{', a nested block, and '}', and returns the block and the location of '}'
+    fn body(&mut self) -> Result<(Vec<Statement>, Span), MacroError> {
+        self.expect(Kind::OpenBrace)?;
+        let body = self.block(false)?;
+        let end = self.expect(Kind::CloseBrace)?;
+        Ok((body, end))
+    }
+
+    fn statement(&mut self, top_level: bool) -> Result<Statement, MacroError> {
+        let Kind::Word(word) = self.peek().kind.clone() else {
+            return Err(self.expected("a statement"));
+        };
+        let span = self.advance().span;
+
+        if (word == "var" || word == "macro") && !top_level {
+            return Err(MacroError::NotAtTopLevel { span });
+        }
+
+        match word.as_str() {
+            "var" => {
+                let mut names = vec![self.name()?];
+                while self.peek().kind == Kind::Comma {
+                    self.advance();
+                    names.push(self.name()?);
+                }
+                Ok(Statement::Var { names })
+            }
+            "macro" => {
+                let (name, name_span) = self.name()?;
+                if self.arities.contains_key(&name) {
+                    return Err(MacroError::DuplicateName {
+                        name,
+                        span: name_span,
+                    });
+                }
+
+                let mut parameters: Vec<String> = Vec::new();
+                if self.peek().kind != Kind::OpenBrace {
+                    loop {
+                        let (parameter, span) = self.name()?;
+                        if parameters.contains(&parameter) {
+                            return Err(MacroError::DuplicateName {
+                                name: parameter,
+                                span,
+                            });
+                        }
+                        parameters.push(parameter);
+
+                        if self.peek().kind != Kind::Comma {
+                            break;
+                        }
+                        self.advance();
+                    }
+                }
+                // the macro can be called in its own body, which is reported when it is expanded
+                self.arities.insert(name.clone(), parameters.len());
+                let (body, _) = self.body()?;
+
+                Ok(Statement::Macro {
+                    name,
+                    parameters,
+                    body,
+                })
+            }
+            "print" => {
+                let Kind::Text(text) = self.peek().kind.clone() else {
+                    return Err(self.expected("a string"));
+                };
+                self.advance();
+                Ok(Statement::Print { text, span })
+            }
+            _ => {
+                let arity = match BUILTINS.iter().find(|(builtin, _)| *builtin == word) {
+                    Some((_, arity)) => *arity,
+                    None => *self
+                        .arities
+                        .get(&word)
+                        .ok_or(MacroError::UnknownStatement {
+                            name: word.clone(),
+                            span,
+                        })?,
+                };
+
+                // operands of builtins and macro calls are separated by commas alike
+                let mut operands = Vec::new();
+                for index in 0..arity {
+                    if index > 0 {
+                        self.expect(Kind::Comma)?;
+                    }
+                    operands.push(self.operand()?);
+                }
+
+                let (body, end) = match word.as_str() {
+                    "while" | "if" => self.body()?,
+                    _ => (Vec::new(), span),
+                };
+                Ok(Statement::Call {
+                    name: word,
+                    operands,
+                    body,
+                    span,
+                    end,
+                })
+            }
+        }
+    }
+}
+
+struct Macro<'a> {
+    parameters: &'a [String],
+    body: &'a [Statement],
+}
+
+/// Operands of a macro call, by parameter name
+type Bindings = HashMap<String, Operand>;
+
+struct Compiler<'a> {
+    code: String,
+    source_map: Vec<Span>,
+    /// Cell the pointer is at, every statement knows where it leaves the pointer
+    pointer: usize,
+    variables: HashMap<String, usize>,
+    macros: HashMap<String, Macro<'a>>,
+    /// First cell right of the variables and the temporaries in use
+    next_temporary: usize,
+    /// Names of the macros being expanded
+    expanding: Vec<String>,
+}
+
+impl<'a> Compiler<'a> {
+    /// Assigns cells to the variables and collects the macros
+    fn declare(&mut self, statements: &'a [Statement]) -> Result<(), MacroError> {
+        for statement in statements {
+            match statement {
+                Statement::Var { names } => {
+                    for (name, span) in names {
+                        if self.variables.contains_key(name) {
+                            return Err(MacroError::DuplicateName {
+                                name: name.clone(),
+                                span: *span,
+                            });
+                        }
+                        self.variables.insert(name.clone(), self.variables.len());
+                    }
+                }
+                Statement::Macro {
+                    name,
+                    parameters,
+                    body,
+                    ..
+                } => {
+                    self.macros.insert(name.clone(), Macro { parameters, body });
+                }
+                _ => {}
+            }
+        }
+        self.next_temporary = self.variables.len();
+        Ok(())
+    }
+
+    fn emit(&mut self, code: &str, span: Span) {
+        self.code.push_str(code);
+        self.source_map
+            .extend(std::iter::repeat_n(span, code.chars().count()));
+    }
+
+    fn move_to(&mut self, cell: usize, span: Span) {
+        let movement = if cell > self.pointer { ">" } else { "<" };
+        self.emit(&movement.repeat(self.pointer.abs_diff(cell)), span);
+        self.pointer = cell;
+    }
+
+    fn add(&mut self, cell: usize, amount: i64, span: Span) {
+        self.move_to(cell, span);
+        let adjustment = if amount > 0 { "+" } else { "-" };
+        self.emit(&adjustment.repeat(amount.unsigned_abs() as usize), span);
+    }
+
+    fn clear(&mut self, cell: usize, span: Span) {
+        self.move_to(cell, span);
+        self.emit("[-]", span);
+    }
+
+    /// Adds the value of a cell to other cells and clears it
+    fn transfer(&mut self, from: usize, to: &[usize], span: Span) {
+        self.move_to(from, span);
+        self.emit("[-", span);
+        for &cell in to {
+            self.add(cell, 1, span);
+        }
+        self.move_to(from, span);
+        self.emit("]", span);
+    }
+
+    fn allocate(&mut self) -> usize {
+        self.next_temporary += 1;
+        self.next_temporary - 1
+    }
+
+    fn release(&mut self) {
+        self.next_temporary -= 1;
+    }
+
+    /// Sets a cell to the value of another cell using a temporary cell
+    fn copy(&mut self, from: usize, to: usize, span: Span) {
+        if from == to {
+            return;
+        }
+        let temporary = self.allocate();
+        self.clear(to, span);
+        self.transfer(from, &[to, temporary], span);
+        self.transfer(temporary, &[from], span);
+        self.release();
+    }
+
+    fn cell(&self, operand: &Operand, bindings: &Bindings) -> Result<usize, MacroError> {
+        match operand {
+            Operand::Name(name, span) => match bindings.get(name) {
+                // operands of a macro call are resolved where the macro was called
+                Some(operand) => self.cell(operand, &HashMap::new()),
+                None => self
+                    .variables
+                    .get(name)
+                    .copied()
+                    .ok_or(MacroError::UnknownVariable {
+                        name: name.clone(),
+                        span: *span,
+                    }),
+            },
+            Operand::Number(_, span) => Err(MacroError::Expected {
+                expected: String::from("a variable"),
+                found: String::from("a number"),
+                span: *span,
+            }),
+        }
+    }
+
+    fn number(&self, operand: &Operand, bindings: &Bindings) -> Result<i64, MacroError> {
+        match operand {
+            Operand::Number(number, _) => Ok(*number),
+            Operand::Name(name, span) => match bindings.get(name) {
+                Some(operand) => self.number(operand, &HashMap::new()),
+                None => Err(MacroError::Expected {
+                    expected: String::from("a number"),
+                    found: format!("'{}'", name),
+                    span: *span,
+                }),
+            },
+        }
+    }
+
+    fn block(
+        &mut self,
+        statements: &'a [Statement],
+        bindings: &Bindings,
+    ) -> Result<(), MacroError> {
+        for statement in statements {
+            self.statement(statement, bindings)?;
+        }
+        Ok(())
+    }
+
+    fn statement(
+        &mut self,
+        statement: &'a Statement,
+        bindings: &Bindings,
+    ) -> Result<(), MacroError> {
+        let (name, operands, body, span, end) = match statement {
+            Statement::Var { .. } | Statement::Macro { .. } => return Ok(()),
+            Statement::Print { text, span } => {
+                let temporary = self.allocate();
+                let mut value = 0;
+                for &byte in text {
+                    self.add(temporary, byte as i64 - value, *span);
+                    self.emit(".", *span);
+                    value = byte as i64;
+                }
+                self.clear(temporary, *span);
+                self.release();
+                return Ok(());
+            }
+            Statement::Call {
+                name,
+                operands,
+                body,
+                span,
+                end,
+            } => (name, operands, body, *span, *end),
+        };
+
+        match name.as_str() {
+            "add" | "sub" | "set" => {
+                let cell = self.cell(&operands[0], bindings)?;
+                let number = self.number(&operands[1], bindings)?;
+                match name.as_str() {
+                    "add" => self.add(cell, number, span),
+                    // numbers are limited to MAX_NUMBER, so they can be negated
+                    "sub" => self.add(cell, -number, span),
+                    _ => {
+                        self.clear(cell, span);
+                        self.add(cell, number, span);
+                    }
+                }
+            }
+            "copy" => {
+                let from = self.cell(&operands[0], bindings)?;
+                let to = self.cell(&operands[1], bindings)?;
+                self.copy(from, to, span);
+            }
+            "out" | "in" => {
+                let cell = self.cell(&operands[0], bindings)?;
+                self.move_to(cell, span);
+                self.emit(if name == "out" { "." } else { "," }, span);
+            }
+            "while" => {
+                let cell = self.cell(&operands[0], bindings)?;
+                self.move_to(cell, span);
+                self.emit("[", span);
+                self.block(body, bindings)?;
+                self.move_to(cell, end);
+                self.emit("]", end);
+            }
+            "if" => {
+                // the block runs on a copy, so it may change the condition
+                let cell = self.cell(&operands[0], bindings)?;
+                let condition = self.allocate();
+                self.copy(cell, condition, span);
+                self.move_to(condition, span);
+                self.emit("[", span);
+                self.block(body, bindings)?;
+                self.clear(condition, end);
+                self.emit("]", end);
+                self.release();
+            }
+            _ => self.expand(name, operands, span, bindings)?,
+        }
+        Ok(())
+    }
+
+    fn expand(
+        &mut self,
+        name: &str,
+        operands: &[Operand],
+        span: Span,
+        bindings: &Bindings,
+    ) -> Result<(), MacroError> {
+        if self.expanding.iter().any(|expanding| expanding == name) {
+            return Err(MacroError::RecursiveMacro {
+                name: name.to_string(),
+                span,
+            });
+        }
+        let Some(definition) = self.macros.get(name) else {
+            return Err(MacroError::UnknownStatement {
+                name: name.to_string(),
+                span,
+            });
+        };
+        let (parameters, body) = (definition.parameters, definition.body);
+
+        // operands that name parameters of the calling macro are replaced by their operands
+        let mut arguments = Bindings::new();
+        for (parameter, operand) in parameters.iter().zip(operands) {
+            let operand = match operand {
+                Operand::Name(name, _) => bindings.get(name).unwrap_or(operand),
+                Operand::Number(..) => operand,
+            };
+            arguments.insert(parameter.clone(), operand.clone());
+        }
+
+        self.expanding.push(name.to_string());
+        self.block(body, &arguments)?;
+        self.expanding.pop();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lex, Interpreter, InterpreterOptions};
+    use std::io::{self, Cursor};
+
+    fn run(source: &str, input: &[u8]) -> Vec<u8> {
+        let assembly = compile_macros(source, CellWidth::Eight).unwrap();
+        let tokens = lex(&assembly.code);
+        let options = InterpreterOptions::new()
+            .reader(Cursor::new(input.to_vec()))
+            .writer(io::sink());
+        let mut interpreter = Interpreter::new(&tokens, options).unwrap();
+        interpreter.interpret().unwrap()
+    }
+
+    #[test]
+    fn test_print() {
+        assert_eq!(run("print \"Hi!\\n\" print \"ok\"", b""), b"Hi!\nok");
+    }
+
+    #[test]
+    fn test_variables() {
+        let source = "
+            var a, b
+            set a, 65
+            add a, 2
+            sub a, 1   # 66
+            copy a, b
+            add b, 1   # 67
+            out a out b
+            in a out a
+        ";
+        assert_eq!(run(source, b"x"), b"BCx");
+    }
+
+    #[test]
+    fn test_while_and_if() {
+        let source = "
+            var counter, letter, flag
+            set counter, 3
+            set letter, 97
+            while counter {
+                out letter
+                add letter, 1
+                sub counter, 1
+            }
+            if letter { print \"yes\" set letter, 0 }
+            if letter { print \"no\" }
+            if flag { print \"no\" }
+            out letter
+        ";
+        assert_eq!(run(source, b""), b"abcyes\0");
+    }
+
+    #[test]
+    fn test_macros() {
+        let source = "
+            var x, y
+            macro twice cell, amount { add cell, amount add cell, amount }
+            macro print_sum first, second, result {
+                copy first, result
+                while second { add result, 1 sub second, 1 }
+                out result
+            }
+            set x, 30
+            twice x, 2
+            set y, 3
+            print_sum x, y, x
+        ";
+        // x is 34 and doubles as the result, 34 + 3 = 37 is '%'
+        assert_eq!(run(source, b""), b"%");
+    }
+
+    #[test]
+    fn test_source_map() {
+        let source = "var a\nadd a, 2\nwhile a {\n  sub a, 1\n}";
+        let assembly = compile_macros(source, CellWidth::Eight).unwrap();
+        assert_eq!(assembly.code, "++[-]");
+        assert_eq!(assembly.source_map.len(), assembly.code.len());
+
+        let lines: Vec<usize> = assembly.source_map.iter().map(|span| span.line).collect();
+        assert_eq!(lines, vec![2, 2, 3, 4, 5]);
+        assert_eq!(assembly.source_map[3].column, 3);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |source: &str| compile_macros(source, CellWidth::Eight).unwrap_err();
+
+        assert!(matches!(
+            error("var a add b, 1"),
+            MacroError::UnknownVariable { .. }
+        ));
+        assert!(matches!(
+            error("jump 3"),
+            MacroError::UnknownStatement { .. }
+        ));
+        assert!(matches!(
+            error("var a, a"),
+            MacroError::DuplicateName { .. }
+        ));
+        assert!(matches!(
+            error("macro twice x, x { add x, 2 }"),
+            MacroError::DuplicateName { .. }
+        ));
+        assert!(matches!(
+            error("print \"open"),
+            MacroError::UnterminatedString { .. }
+        ));
+        assert!(matches!(
+            error("var a while a { var b }"),
+            MacroError::NotAtTopLevel { .. }
+        ));
+        assert!(matches!(
+            error("var a add a, a"),
+            MacroError::Expected { .. }
+        ));
+        assert!(matches!(
+            error("var a add a, 1x"),
+            MacroError::InvalidNumber { .. }
+        ));
+        assert!(matches!(
+            error("var a ; "),
+            MacroError::UnexpectedCharacter { .. }
+        ));
+        assert!(matches!(
+            error("var a add a 1"),
+            MacroError::Expected { .. }
+        ));
+        assert!(matches!(
+            error("var a sub a, -9223372036854775808"),
+            MacroError::NumberOutOfRange { .. }
+        ));
+        assert!(matches!(
+            error("var a add a, 99999999999999999999"),
+            MacroError::NumberOutOfRange { .. }
+        ));
+
+        // numbers must fit into a cell, and are limited to 16 bits for any cell width
+        assert!(matches!(
+            error("var a set a, 300"),
+            MacroError::NumberOutOfRange { max: 255, .. }
+        ));
+        assert!(compile_macros("var a set a, 255 sub a, -255", CellWidth::Eight).is_ok());
+        assert!(compile_macros("var a set a, 300", CellWidth::Sixteen).is_ok());
+        assert!(matches!(
+            compile_macros("var a add a, 65536", CellWidth::ThirtyTwo),
+            Err(MacroError::NumberOutOfRange { max: 65535, .. })
+        ));
+
+        // errors at the end of the program point there instead of at the last lexeme
+        for source in ["print", "var a while a {"] {
+            match error(source) {
+                MacroError::Expected { found, span, .. } => {
+                    assert_eq!(found, "the end of the program");
+                    assert_eq!(span.start, source.len());
+                }
+                error => panic!("unexpected error {}", error),
+            }
+        }
+
+        let error = error("macro loop { loop }\nloop");
+        assert!(matches!(error, MacroError::RecursiveMacro { .. }));
+        assert_eq!(error.span().line, 1);
+    }
+}
@@ -0,0 +1,289 @@
+use std::fmt;
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::token::{Token, TokenValue};
+
+/// Cells the pointer can be at when an instruction is executed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PointerRange {
+    pub min: usize,
+    /// `usize::MAX` if the pointer is not bounded on a growable tape
+    pub max: usize,
+}
+
+impl PointerRange {
+    fn at(cell: usize) -> PointerRange {
+        PointerRange {
+            min: cell,
+            max: cell,
+        }
+    }
+
+    fn join(&self, other: &PointerRange) -> PointerRange {
+        PointerRange {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+}
+
+impl fmt::Display for PointerRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.max {
+            usize::MAX => write!(f, "{}..", self.min),
+            max if max == self.min => write!(f, "{}", max),
+            max => write!(f, "{}..={}", self.min, max),
+        }
+    }
+}
+
+/// Computes the cells the pointer can be at before each token is executed, None for tokens that
+/// are only reached by moving the pointer off the tape
+///
+/// Loops are assumed to run any number of times. A loop that moves the pointer by the same
+/// distance in every iteration, e.g. `[<]`, could reach any cell in that direction, so the
+/// ranges are only tight for programs whose loops return the pointer to where they started.
+/// Pointer movements that would leave the tape stop the program, so the ranges after them are
+/// limited to the tape.
+///
+/// # Arguments
+///
+/// * `tokens` - The token stream of a brainfuck program with balanced brackets
+/// * `tape_length` - Number of cells of the tape, None if it grows to the right
+pub fn pointer_ranges(tokens: &[Token], tape_length: Option<usize>) -> Vec<Option<PointerRange>> {
+    let mut analysis = Analysis {
+        tokens,
+        last_cell: tape_length.map_or(usize::MAX, |length| length.saturating_sub(1)),
+        ranges: vec![None; tokens.len()],
+    };
+    analysis.block(0, tokens.len(), PointerRange::at(0));
+    analysis.ranges
+}
+
+/// Returns warnings for every instruction that could move the pointer off the tape
+///
+/// # Arguments
+///
+/// * `tokens` - The token stream of a brainfuck program with balanced brackets
+/// * `ranges` - The pointer ranges of the tokens computed by `pointer_ranges`
+/// * `tape_length` - Number of cells of the tape, None if it grows to the right
+pub fn bounds_warnings(
+    tokens: &[Token],
+    ranges: &[Option<PointerRange>],
+    tape_length: Option<usize>,
+) -> Vec<Diagnostic> {
+    let mut warnings = Vec::new();
+
+    for (token, range) in tokens.iter().zip(ranges) {
+        let Some(range) = range else {
+            continue;
+        };
+        let cells = if range.min == range.max {
+            "cell"
+        } else {
+            "cells"
+        };
+        let label = format!("the pointer can be at {} {} here", cells, range);
+        match token.value {
+            TokenValue::MoveLeft if range.min == 0 => warnings.push(
+                Diagnostic::new(
+                    "W0007",
+                    Severity::Warning,
+                    "the pointer can move below cell 0",
+                )
+                .with_primary(token.span, &label),
+            ),
+            TokenValue::MoveRight if tape_length.is_some_and(|length| range.max >= length - 1) => {
+                warnings.push(
+                    Diagnostic::new(
+                        "W0008",
+                        Severity::Warning,
+                        "the pointer can move past the last cell",
+                    )
+                    .with_primary(token.span, &label)
+                    .with_note(&format!(
+                        "the tape has {} cells",
+                        tape_length.unwrap_or_default()
+                    )),
+                )
+            }
+            _ => continue,
+        }
+    }
+
+    warnings
+}
+
+struct Analysis<'a> {
+    tokens: &'a [Token],
+    /// Index of the last cell, `usize::MAX` for a growable tape
+    last_cell: usize,
+    /// Joined ranges of all visits of a token, None if it was not reached
+    ranges: Vec<Option<PointerRange>>,
+}
+
+impl Analysis<'_> {
+    fn record(&mut self, index: usize, range: PointerRange) {
+        let recorded = &mut self.ranges[index];
+        *recorded = Some(recorded.map_or(range, |recorded| recorded.join(&range)));
+    }
+
+    /// Analyzes the tokens from start to end and returns the range after them, None if the
+    /// pointer leaves the tape for sure
+    fn block(&mut self, start: usize, end: usize, entry: PointerRange) -> Option<PointerRange> {
+        let mut range = entry;
+        let mut index = start;
+
+        while index < end {
+            self.record(index, range);
+            match self.tokens[index].value {
+                TokenValue::MoveLeft => {
+                    if range.max == 0 {
+                        return None;
+                    }
+                    range = PointerRange {
+                        min: range.min.saturating_sub(1),
+                        max: match range.max {
+                            usize::MAX => usize::MAX,
+                            max => max - 1,
+                        },
+                    };
+                }
+                TokenValue::MoveRight => {
+                    if range.min >= self.last_cell {
+                        return None;
+                    }
+                    range = PointerRange {
+                        min: range.min + 1,
+                        max: range.max.saturating_add(1).min(self.last_cell),
+                    };
+                }
+                TokenValue::JumpForwardIfZero => {
+                    let closing = self.matching_bracket(index);
+                    range = self.repeat(index, closing, range);
+                    self.record(closing, range);
+                    index = closing;
+                }
+                _ => {}
+            }
+            index += 1;
+        }
+
+        Some(range)
+    }
+
+    /// Analyzes a loop until the range at its start doesn't change and returns the range after
+    /// the loop
+    fn repeat(&mut self, opening: usize, closing: usize, entry: PointerRange) -> PointerRange {
+        let mut head = entry;
+        loop {
+            let Some(body) = self.block(opening + 1, closing, head) else {
+                // the loop only ends when it is never entered
+                return head;
+            };
+
+            // bounds that keep moving are widened to the end of the tape, so this terminates
+            let mut next = head.join(&body);
+            if next.min < head.min {
+                next.min = 0;
+            }
+            if next.max > head.max {
+                next.max = self.last_cell;
+            }
+
+            if next == head {
+                return head;
+            }
+            head = next;
+        }
+    }
+
+    fn matching_bracket(&self, start: usize) -> usize {
+        let mut depth = 0;
+        for (index, token) in self.tokens.iter().enumerate().skip(start) {
+            match token.value {
+                TokenValue::JumpForwardIfZero => depth += 1,
+                TokenValue::JumpBackwardIfNonZero => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return index;
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.tokens.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex;
+
+    fn ranges(source: &str, tape_length: Option<usize>) -> Vec<Option<PointerRange>> {
+        pointer_ranges(&lex(source), tape_length)
+    }
+
+    fn range(min: usize, max: usize) -> Option<PointerRange> {
+        Some(PointerRange { min, max })
+    }
+
+    #[test]
+    fn test_straight_line() {
+        let ranges = ranges(">>+<.", Some(10));
+        assert_eq!(
+            ranges,
+            vec![
+                range(0, 0),
+                range(1, 1),
+                range(2, 2),
+                range(2, 2),
+                range(1, 1),
+                range(1, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_balanced_loops() {
+        let source = "++[>+++[>+<-]<-]>>.";
+        let ranges = ranges(source, Some(10));
+        assert_eq!(ranges[8], range(1, 1));
+        assert_eq!(ranges[9], range(2, 2));
+        assert_eq!(ranges[ranges.len() - 2], range(2, 2));
+        assert!(bounds_warnings(&lex(source), &ranges, Some(10)).is_empty());
+    }
+
+    #[test]
+    fn test_unbalanced_loops() {
+        // a scan to the right could reach any cell
+        let ranges = ranges("+[>+]<", Some(10));
+        assert_eq!(ranges[2], range(0, 9));
+        assert_eq!(ranges[5], range(0, 9));
+
+        let ranges = self::ranges("+[>+]<", None);
+        assert_eq!(ranges[5], range(0, usize::MAX));
+        assert_eq!(ranges[5].unwrap().to_string(), "0..");
+    }
+
+    #[test]
+    fn test_warnings() {
+        let source = "+[>+]>><<<";
+        let tokens = lex(source);
+        let ranges = pointer_ranges(&tokens, Some(10));
+        let warnings = bounds_warnings(&tokens, &ranges, Some(10));
+        let codes: Vec<&str> = warnings.iter().map(|warning| warning.code).collect();
+        assert_eq!(codes, vec!["W0008", "W0008", "W0008", "W0007"]);
+
+        // a growable tape has no last cell
+        let ranges = pointer_ranges(&tokens, None);
+        assert_eq!(bounds_warnings(&tokens, &ranges, None).len(), 1);
+
+        // the second '<' is never reached
+        let tokens = lex("<<");
+        let ranges = pointer_ranges(&tokens, Some(10));
+        assert_eq!(ranges[1], None);
+        assert_eq!(bounds_warnings(&tokens, &ranges, Some(10)).len(), 1);
+    }
+}
@@ -0,0 +1,74 @@
+use custom_error::custom_error;
+
+use crate::token::{Span, TokenValue};
+use crate::Token;
+
+custom_error! { pub SyntaxError
+    UnmatchedOpening{index: usize, span: Span, end: Span} =
+        @{ format!("unmatched '[' at line {}, column {}", span.line, span.column) },
+    UnmatchedClosing{index: usize, span: Span} =
+        @{ format!("unmatched ']' at line {}, column {}", span.line, span.column) }
+}
+
+/// Checks that every [ in the token stream has a matching ] and vice versa
+///
+/// # Arguments
+///
+/// * `tokens` - The token stream of a brainfuck program
+pub fn check_brackets(tokens: &[Token]) -> Result<(), SyntaxError> {
+    let mut open_brackets = Vec::new();
+
+    for (index, token) in tokens.iter().enumerate() {
+        match token.value {
+            TokenValue::JumpForwardIfZero => open_brackets.push(index),
+            TokenValue::JumpBackwardIfNonZero => {
+                open_brackets.pop().ok_or(SyntaxError::UnmatchedClosing {
+                    index,
+                    span: token.span,
+                })?;
+            }
+            _ => {}
+        }
+    }
+
+    match open_brackets.pop() {
+        Some(index) => Err(SyntaxError::UnmatchedOpening {
+            index,
+            span: tokens[index].span,
+            end: tokens.last().map_or(Span::default(), |token| token.span),
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex;
+
+    #[test]
+    fn test_balanced_brackets() {
+        assert!(check_brackets(&lex("+[->[-]<]")).is_ok());
+        assert!(check_brackets(&lex("no commands at all")).is_ok());
+    }
+
+    #[test]
+    fn test_unmatched_brackets() {
+        match check_brackets(&lex("+[->[-]<")) {
+            Err(SyntaxError::UnmatchedOpening { index, span, end }) => {
+                assert_eq!(index, 1);
+                assert_eq!((span.line, span.column), (1, 2));
+                assert_eq!(end.start, 8);
+            }
+            _ => panic!("the first [ is never closed"),
+        }
+
+        match check_brackets(&lex("+[-]]")) {
+            Err(SyntaxError::UnmatchedClosing { index, span }) => {
+                assert_eq!(index, 4);
+                assert_eq!((span.line, span.column), (1, 5));
+            }
+            _ => panic!("the second ] is never opened"),
+        }
+    }
+}
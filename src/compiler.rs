@@ -0,0 +1,150 @@
+use crate::interpreter::EofPolicy;
+use crate::state::CellWidth;
+use crate::token::TokenValue;
+use crate::Token;
+
+/// Compiles a token stream into an equivalent C program
+///
+/// Runs of `+`/`-` and `<`/`>` are folded into single statements. The generated program uses
+/// wrapping cells and does not check the bounds of the tape.
+///
+/// # Arguments
+///
+/// * `tokens` - The token stream of a brainfuck program with balanced brackets
+/// * `tape_length` - Number of cells of the tape
+/// * `cell_width` - Width of a single cell
+/// * `eof_policy` - Behaviour of `,` when the input is exhausted
+pub fn compile_to_c(
+    tokens: &[Token],
+    tape_length: usize,
+    cell_width: CellWidth,
+    eof_policy: EofPolicy,
+) -> String {
+    let cell_type = match cell_width {
+        CellWidth::Eight => "uint8_t",
+        CellWidth::Sixteen => "uint16_t",
+        CellWidth::ThirtyTwo => "uint32_t",
+    };
+
+    let mut c = String::new();
+    c.push_str("#include <stdint.h>\n#include <stdio.h>\n\n");
+    c.push_str(&format!("static {} tape[{}];\n\n", cell_type, tape_length));
+    c.push_str("int main(void) {\n");
+    c.push_str(&format!("    {} *p = tape;\n", cell_type));
+    c.push_str("    int c;\n");
+
+    let mut indentation = 1;
+    let mut index = 0;
+    while index < tokens.len() {
+        let value = &tokens[index].value;
+
+        // fold runs of the same instruction into a single statement
+        let mut count = 1;
+        if is_foldable(value) {
+            while index + count < tokens.len() && tokens[index + count].value == *value {
+                count += 1;
+            }
+        }
+        index += count;
+
+        let statement = match value {
+            TokenValue::MoveRight => format!("p += {};", count),
+            TokenValue::MoveLeft => format!("p -= {};", count),
+            TokenValue::IncrementCell => format!("*p += {};", count),
+            TokenValue::DecrementCell => format!("*p -= {};", count),
+            TokenValue::Output => String::from("putchar((unsigned char)*p);"),
+            TokenValue::Input => {
+                let on_eof = match eof_policy {
+                    EofPolicy::Unchanged => String::from(""),
+                    EofPolicy::Zero => String::from(" else { *p = 0; }"),
+                    EofPolicy::MaxValue => format!(" else {{ *p = ({})-1; }}", cell_type),
+                    EofPolicy::Error => String::from(
+                        " else { fputs(\"end of input reached\\n\", stderr); return 1; }",
+                    ),
+                };
+                format!(
+                    "fflush(stdout); c = getchar(); if (c != EOF) {{ *p = ({})c; }}{}",
+                    cell_type, on_eof
+                )
+            }
+            TokenValue::JumpForwardIfZero => String::from("while (*p) {"),
+            TokenValue::JumpBackwardIfNonZero => {
+                indentation -= 1;
+                String::from("}")
+            }
+            TokenValue::End => continue,
+        };
+
+        c.push_str(&"    ".repeat(indentation));
+        c.push_str(&statement);
+        c.push('\n');
+
+        if *value == TokenValue::JumpForwardIfZero {
+            indentation += 1;
+        }
+    }
+
+    c.push_str("    return 0;\n}\n");
+    c
+}
+
+fn is_foldable(value: &TokenValue) -> bool {
+    matches!(
+        value,
+        TokenValue::MoveRight
+            | TokenValue::MoveLeft
+            | TokenValue::IncrementCell
+            | TokenValue::DecrementCell
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex;
+
+    #[test]
+    fn test_compile_to_c() {
+        let c = compile_to_c(
+            &lex("+++[->>+<<]>>."),
+            100,
+            CellWidth::Eight,
+            EofPolicy::Unchanged,
+        );
+
+        assert_eq!(
+            c,
+            "\
+#include <stdint.h>
+#include <stdio.h>
+
+static uint8_t tape[100];
+
+int main(void) {
+    uint8_t *p = tape;
+    int c;
+    *p += 3;
+    while (*p) {
+        *p -= 1;
+        p += 2;
+        *p += 1;
+        p -= 2;
+    }
+    p += 2;
+    putchar((unsigned char)*p);
+    return 0;
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_compile_input() {
+        let c = compile_to_c(&lex(","), 10, CellWidth::Sixteen, EofPolicy::Zero);
+
+        assert!(c.contains("static uint16_t tape[10];"));
+        assert!(c.contains(
+            "fflush(stdout); c = getchar(); if (c != EOF) { *p = (uint16_t)c; } else { *p = 0; }"
+        ));
+    }
+}
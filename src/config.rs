@@ -1,17 +1,936 @@
+use custom_error::custom_error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::config_file;
+use crate::diagnostic::ErrorFormat;
+use crate::dialect::Dialect;
+use crate::formatter::DEFAULT_WIDTH;
+use crate::interpreter::EofPolicy;
+use crate::state::{BoundsMode, CellWidth, OverflowMode, DEFAULT_TAPE_LENGTH};
+use crate::CancellationToken;
+use crate::FlushPolicy;
+
+pub const USAGE: &str = "\
+Usage: brainfuck_rust [COMMAND] [OPTIONS] <FILE>
+       brainfuck_rust [COMMAND] [OPTIONS] -e <PROGRAM>
+
+The program is read from stdin if FILE is '-'. Settings are read from the nearest
+brainfuck.toml in the current directory or its parents, flags take precedence.
+A switch enabled in the config file is turned off with --no-<SWITCH>, e.g. --no-macros.
+A line '#include \"PATH\"' is replaced with the commands of the file at PATH.
+
+Commands:
+  run        Run a brainfuck program (default)
+  check      Check a brainfuck program for unmatched brackets
+  fmt        Format a brainfuck program in place, or print it if it is read from stdin
+  minify     Print a brainfuck program without comments and redundant instructions
+  lint       Warn about suspicious code in a brainfuck program
+  bounds     Check statically whether the pointer can leave the tape
+  generate   Generate a brainfuck program that prints a text, given like a program
+  assemble   Compile a program in the macro language to brainfuck
+  compile    Compile a brainfuck program to C
+  debug      Run a brainfuck program and trace every instruction to stderr
+  repl       Start an interactive brainfuck session
+  profile    Run a brainfuck program and report instruction counts to stderr
+
+Options:
+  -e, --execute <PROGRAM>   Use PROGRAM as the brainfuck source instead of a file
+  --dialect <DIALECT>       Use the tape and cell semantics of another interpreter:
+                            original, bff, portable or beef, other flags override it
+  --tape-size <CELLS>       Number of cells of the tape [default: 30000]
+  --growable-tape           Grow the tape on demand instead of failing at its end
+  --max-tape-bytes <BYTES>  Upper bound for the memory of a growable tape
+  --cell-width <BITS>       Width of a cell: 8, 16 or 32 [default: 8]
+  --overflow <MODE>         Cell overflow behaviour: error or wrap [default: error]
+  --eof <POLICY>            Behaviour of ',' at the end of the input:
+                            unchanged, zero, max or error [default: unchanged]
+  --input <FILE>            Read the program input from a file instead of stdin
+  --embedded-input          Use everything after the first '!' in the program as input
+  --macros                  Compile the program from the macro language before running it
+  --includes                Replace #include \"FILE\" lines with the contents of FILE
+  --output <FILE>           Write the output to a file instead of stdout
+  --flush <POLICY>          Output flush policy: newline, input, exit or unbuffered
+                            [default: newline]
+  --max-steps <COUNT>       Abort after executing this many instructions
+  --timeout <MILLISECONDS>  Abort after running this long
+  --detect-infinite-loops   Abort when a loop iteration repeats the state of the previous one
+  --error-format <FORMAT>   Format of error messages: human or json [default: human]
+  --exit-code-from-cell     Exit with the value of the current cell at the end of the program
+  --width <COLUMNS>         Maximum line length of code formatted by fmt [default: 80]
+  --check                   Only check whether fmt would change the program
+  --config <FILE>           Read settings from FILE instead of the nearest brainfuck.toml
+  --no-config               Ignore brainfuck.toml files
+  -h, --help                Print this help
+  -V, --version             Print the version
+
+Exit codes:
+  0  Success                   5  Runtime fault
+  1  Other error               6  Step or memory limit exceeded
+  2  Invalid usage             7  Timeout or cancellation
+  3  I/O error                 8  Warnings, or fmt --check found unformatted code
+  4  Syntax error";
+
+custom_error! { pub ConfigError
+    MissingValue{flag: String} = "{flag} requires a value.",
+    InvalidValue{flag: String, value: String, expected: &'static str} =
+        "invalid value '{value}' for {flag} (expected {expected}).",
+    UnknownFlag{flag: String} = "unknown flag {flag}.",
+    UnexpectedArgument{argument: String} = "unexpected argument '{argument}'.",
+    MissingProgram = "no path to brainfuck file or inline program provided.",
+    RequiresGrowableTape = "--max-tape-bytes requires --growable-tape.",
+    ConflictingInput = "--embedded-input cannot be combined with --input.",
+    ConflictingMacros = "--macros cannot be combined with --embedded-input.",
+    CheckRequiresFmt = "--check can only be used with fmt.",
+    InvalidConfigFile{path: String, message: String} = "invalid config file {path}: {message}"
+}
+
+/// Action selected on the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Command {
+    #[default]
+    Run,
+    Check,
+    Fmt,
+    Minify,
+    Lint,
+    Bounds,
+    Generate,
+    Assemble,
+    Compile,
+    Debug,
+    Repl,
+    Profile,
+    Help,
+    Version,
+}
+
+impl Command {
+    fn from_name(name: &str) -> Option<Command> {
+        match name {
+            "run" => Some(Command::Run),
+            "check" => Some(Command::Check),
+            "fmt" => Some(Command::Fmt),
+            "minify" => Some(Command::Minify),
+            "lint" => Some(Command::Lint),
+            "bounds" => Some(Command::Bounds),
+            "generate" => Some(Command::Generate),
+            "assemble" => Some(Command::Assemble),
+            "compile" => Some(Command::Compile),
+            "debug" => Some(Command::Debug),
+            "repl" => Some(Command::Repl),
+            "profile" => Some(Command::Profile),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the command operates on a brainfuck file
+    pub fn needs_program(&self) -> bool {
+        !matches!(self, Command::Repl | Command::Help | Command::Version)
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Command::Run => "run",
+            Command::Check => "check",
+            Command::Fmt => "fmt",
+            Command::Minify => "minify",
+            Command::Lint => "lint",
+            Command::Bounds => "bounds",
+            Command::Generate => "generate",
+            Command::Assemble => "assemble",
+            Command::Compile => "compile",
+            Command::Debug => "debug",
+            Command::Repl => "repl",
+            Command::Profile => "profile",
+            Command::Help => "help",
+            Command::Version => "version",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 pub struct Config {
+    pub command: Command,
     pub brainfuck_file_path: String,
+    pub inline_program: Option<String>,
+    pub input_file_path: Option<String>,
+    pub embedded_input: bool,
+    pub macros: bool,
+    pub includes: bool,
+    pub output_file_path: Option<String>,
+    pub dialect: Option<Dialect>,
+    pub tape_size: usize,
+    pub cell_width: CellWidth,
+    pub overflow_mode: OverflowMode,
+    pub eof_policy: EofPolicy,
+    pub max_steps: Option<u64>,
+    pub timeout: Option<Duration>,
+    pub cancellation_token: Option<CancellationToken>,
+    pub growable_tape: bool,
+    pub max_tape_bytes: Option<usize>,
+    pub flush_policy: FlushPolicy,
+    pub error_format: ErrorFormat,
+    pub detect_infinite_loops: bool,
+    pub exit_code_from_cell: bool,
+    pub format_width: usize,
+    pub check_formatting: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            command: Command::default(),
+            brainfuck_file_path: String::new(),
+            inline_program: None,
+            input_file_path: None,
+            embedded_input: false,
+            macros: false,
+            includes: false,
+            output_file_path: None,
+            dialect: None,
+            tape_size: DEFAULT_TAPE_LENGTH,
+            cell_width: CellWidth::default(),
+            overflow_mode: OverflowMode::default(),
+            eof_policy: EofPolicy::default(),
+            max_steps: None,
+            timeout: None,
+            cancellation_token: None,
+            growable_tape: false,
+            max_tape_bytes: None,
+            flush_policy: FlushPolicy::default(),
+            error_format: ErrorFormat::default(),
+            detect_infinite_loops: false,
+            exit_code_from_cell: false,
+            format_width: DEFAULT_WIDTH,
+            check_formatting: false,
+        }
+    }
 }
 
 impl Config {
-    pub fn build(args: &[String]) -> Result<Config, &'static str> {
-        if args.len() < 2 {
-            return Err("no path to brainfuck file provided.");
+    /// Builds a config from command-line arguments, the settings of a config file are only read
+    /// if it is given with --config
+    pub fn build(args: &[String]) -> Result<Config, ConfigError> {
+        Config::with_config_file(args, None)
+    }
+
+    /// Builds a config from command-line arguments merged on top of the settings of a config file
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - The command-line arguments, including the name of the executable
+    /// * `config_file_path` - Path of the config file, e.g. found with `find_config_file`, it is
+    ///   replaced by --config and ignored with --no-config
+    pub fn with_config_file(
+        args: &[String],
+        config_file_path: Option<&Path>,
+    ) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        let mut brainfuck_file_path = None;
+        let mut config_file_path = config_file_path.map(Path::to_path_buf);
+        let mut no_config = false;
+        let mut settings = Vec::new();
+
+        let mut args = args.iter().skip(1).peekable();
+
+        // the command is optional, a lone file path runs the program
+        if let Some(command) = args.peek().and_then(|arg| Command::from_name(arg)) {
+            config.command = command;
+            args.next();
+        }
+
+        while let Some(arg) = args.next() {
+            let flag = arg.as_str();
+            if let Some(switch) = negated_switch(flag) {
+                settings.push((switch, "false"));
+                continue;
+            }
+            match flag {
+                "-h" | "--help" => config.command = Command::Help,
+                "-V" | "--version" => config.command = Command::Version,
+                "-e" | "--execute" => {
+                    config.inline_program = Some(next_value(flag, args.next())?.to_string());
+                }
+                "--input" => {
+                    config.input_file_path = Some(next_value(flag, args.next())?.to_string());
+                }
+                "--output" => {
+                    config.output_file_path = Some(next_value(flag, args.next())?.to_string());
+                }
+                "--config" => {
+                    config_file_path = Some(PathBuf::from(next_value(flag, args.next())?));
+                }
+                "--no-config" => no_config = true,
+                "--check" => config.check_formatting = true,
+                _ if is_switch(flag) => settings.push((flag, "true")),
+                _ if is_setting(flag) => settings.push((flag, next_value(flag, args.next())?)),
+                _ if flag.starts_with('-') && flag != "-" => {
+                    return Err(ConfigError::UnknownFlag {
+                        flag: flag.to_string(),
+                    })
+                }
+                _ => match brainfuck_file_path {
+                    None => brainfuck_file_path = Some(arg.clone()),
+                    Some(_) => {
+                        return Err(ConfigError::UnexpectedArgument {
+                            argument: arg.clone(),
+                        })
+                    }
+                },
+            }
+        }
+
+        // settings of the config file are overridden by flags
+        if let Some(path) = config_file_path.filter(|_| !no_config) {
+            config.apply_config_file(&path)?;
+        }
+        // a dialect sets several settings at once, so it is applied before the others
+        settings.sort_by_key(|(flag, _)| *flag != "--dialect");
+        for (flag, value) in settings {
+            config.apply_setting(flag, value)?;
+        }
+
+        if config.max_tape_bytes.is_some() && !config.growable_tape {
+            return Err(ConfigError::RequiresGrowableTape);
+        }
+
+        if config.embedded_input && config.input_file_path.is_some() {
+            return Err(ConfigError::ConflictingInput);
+        }
+
+        if config.macros && config.embedded_input {
+            return Err(ConfigError::ConflictingMacros);
+        }
+
+        if config.check_formatting && config.command != Command::Fmt {
+            return Err(ConfigError::CheckRequiresFmt);
+        }
+
+        match (brainfuck_file_path, &config.inline_program) {
+            (Some(path), None) => config.brainfuck_file_path = path,
+            (Some(path), Some(_)) => {
+                return Err(ConfigError::UnexpectedArgument { argument: path })
+            }
+            (None, None) if config.command.needs_program() => {
+                return Err(ConfigError::MissingProgram)
+            }
+            (None, _) => {}
+        }
+
+        Ok(config)
+    }
+
+    /// Applies all settings of a config file
+    fn apply_config_file(&mut self, path: &Path) -> Result<(), ConfigError> {
+        let invalid_config_file = |message: String| ConfigError::InvalidConfigFile {
+            path: path.display().to_string(),
+            message,
+        };
+
+        let contents = fs::read_to_string(path).map_err(|e| invalid_config_file(e.to_string()))?;
+        let mut entries =
+            config_file::parse(&contents).map_err(|e| invalid_config_file(e.to_string()))?;
+        entries.sort_by_key(|entry| entry.key != "dialect");
+
+        for entry in entries {
+            if !SETTINGS.contains(&entry.key.as_str()) {
+                return Err(invalid_config_file(format!(
+                    "line {}: unknown setting {}",
+                    entry.line, entry.key
+                )));
+            }
+            self.apply_setting(&entry.key, &entry.value)
+                .map_err(|e| invalid_config_file(format!("line {}: {}", entry.line, e)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies a setting given as flag (e.g. "--tape-size") or config file key (e.g. "tape-size")
+    fn apply_setting(&mut self, flag: &str, value: &str) -> Result<(), ConfigError> {
+        match flag.strip_prefix("--").unwrap_or(flag) {
+            "dialect" => {
+                let dialect = Dialect::from_name(value)
+                    .ok_or_else(|| invalid_value(flag, value, "original, bff, portable or beef"))?;
+                self.apply_dialect(dialect);
+            }
+            "tape-size" => {
+                self.tape_size = parse_value(flag, value, "a positive integer")?;
+                if self.tape_size == 0 {
+                    return Err(invalid_value(flag, value, "a positive integer"));
+                }
+            }
+            "growable-tape" => self.growable_tape = parse_value(flag, value, "true or false")?,
+            "max-tape-bytes" => {
+                self.max_tape_bytes = Some(parse_value(flag, value, "a positive integer")?);
+            }
+            "cell-width" => {
+                self.cell_width = match value {
+                    "8" => CellWidth::Eight,
+                    "16" => CellWidth::Sixteen,
+                    "32" => CellWidth::ThirtyTwo,
+                    _ => return Err(invalid_value(flag, value, "8, 16 or 32")),
+                };
+            }
+            "overflow" => {
+                self.overflow_mode = match value {
+                    "error" => OverflowMode::Error,
+                    "wrap" => OverflowMode::Wrap,
+                    _ => return Err(invalid_value(flag, value, "error or wrap")),
+                };
+            }
+            "eof" => {
+                self.eof_policy = match value {
+                    "unchanged" => EofPolicy::Unchanged,
+                    "zero" => EofPolicy::Zero,
+                    "max" => EofPolicy::MaxValue,
+                    "error" => EofPolicy::Error,
+                    _ => return Err(invalid_value(flag, value, "unchanged, zero, max or error")),
+                };
+            }
+            "embedded-input" => self.embedded_input = parse_value(flag, value, "true or false")?,
+            "macros" => self.macros = parse_value(flag, value, "true or false")?,
+            "includes" => self.includes = parse_value(flag, value, "true or false")?,
+            "flush" => {
+                self.flush_policy = match value {
+                    "newline" => FlushPolicy::Newline,
+                    "input" => FlushPolicy::Input,
+                    "exit" => FlushPolicy::Exit,
+                    "unbuffered" => FlushPolicy::Unbuffered,
+                    _ => {
+                        return Err(invalid_value(
+                            flag,
+                            value,
+                            "newline, input, exit or unbuffered",
+                        ))
+                    }
+                };
+            }
+            "max-steps" => {
+                self.max_steps = Some(parse_value(flag, value, "a positive integer")?);
+            }
+            "timeout" => {
+                let milliseconds = parse_value(flag, value, "a duration in milliseconds")?;
+                self.timeout = Some(Duration::from_millis(milliseconds));
+            }
+            "detect-infinite-loops" => {
+                self.detect_infinite_loops = parse_value(flag, value, "true or false")?
+            }
+            "exit-code-from-cell" => {
+                self.exit_code_from_cell = parse_value(flag, value, "true or false")?
+            }
+            "width" => {
+                self.format_width = parse_value(flag, value, "a positive integer")?;
+                if self.format_width == 0 {
+                    return Err(invalid_value(flag, value, "a positive integer"));
+                }
+            }
+            "error-format" => {
+                self.error_format = match value {
+                    "human" => ErrorFormat::Human,
+                    "json" => ErrorFormat::Json,
+                    _ => return Err(invalid_value(flag, value, "human or json")),
+                };
+            }
+            _ => {
+                return Err(ConfigError::UnknownFlag {
+                    flag: flag.to_string(),
+                })
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets the tape and cell semantics of a dialect
+    fn apply_dialect(&mut self, dialect: Dialect) {
+        let options = dialect.options();
+        self.dialect = Some(dialect);
+        self.tape_size = options.tape_length;
+        self.cell_width = options.cell_width;
+        self.overflow_mode = options.overflow_mode;
+        self.eof_policy = options.eof_policy;
+        self.growable_tape = options.bounds_mode == BoundsMode::Grow;
+    }
+
+    /// Returns a name for the program in messages: its path, "<stdin>", or "<inline>"
+    pub fn program_name(&self) -> &str {
+        match (&self.inline_program, self.brainfuck_file_path.as_str()) {
+            (Some(_), _) => "<inline>",
+            (None, "-") => "<stdin>",
+            (None, path) => path,
         }
+    }
+}
+
+/// Settings that can be given as flag and in a config file, without leading dashes
+const SETTINGS: [&str; 17] = [
+    "dialect",
+    "tape-size",
+    "growable-tape",
+    "max-tape-bytes",
+    "cell-width",
+    "overflow",
+    "eof",
+    "embedded-input",
+    "macros",
+    "includes",
+    "flush",
+    "max-steps",
+    "timeout",
+    "detect-infinite-loops",
+    "error-format",
+    "exit-code-from-cell",
+    "width",
+];
+
+/// Settings that are enabled by a flag without value
+const SWITCHES: [&str; 6] = [
+    "--growable-tape",
+    "--embedded-input",
+    "--macros",
+    "--includes",
+    "--detect-infinite-loops",
+    "--exit-code-from-cell",
+];
+
+fn is_setting(flag: &str) -> bool {
+    flag.strip_prefix("--")
+        .is_some_and(|name| SETTINGS.contains(&name))
+}
+
+fn is_switch(flag: &str) -> bool {
+    SWITCHES.contains(&flag)
+}
+
+/// Returns the switch turned off by a flag like "--no-macros"
+fn negated_switch(flag: &str) -> Option<&'static str> {
+    let name = flag.strip_prefix("--no-")?;
+    SWITCHES
+        .into_iter()
+        .find(|switch| switch.strip_prefix("--") == Some(name))
+}
+
+fn next_value<'a>(flag: &str, value: Option<&'a String>) -> Result<&'a str, ConfigError> {
+    value.map(String::as_str).ok_or(ConfigError::MissingValue {
+        flag: flag.to_string(),
+    })
+}
+
+fn parse_value<T: FromStr>(
+    flag: &str,
+    value: &str,
+    expected: &'static str,
+) -> Result<T, ConfigError> {
+    value
+        .parse()
+        .map_err(|_| invalid_value(flag, value, expected))
+}
+
+fn invalid_value(flag: &str, value: &str, expected: &'static str) -> ConfigError {
+    ConfigError::InvalidValue {
+        flag: flag.to_string(),
+        value: value.to_string(),
+        expected,
+    }
+}
 
-        let brainfuck_file_path = args[1].clone();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_build_limits() {
+        let config = Config::build(&args(&[
+            "brainfuck_rust",
+            "--max-steps",
+            "1000",
+            "program.bf",
+            "--timeout",
+            "250",
+        ]))
+        .unwrap();
+
+        assert_eq!(config.command, Command::Run);
+        assert_eq!(config.brainfuck_file_path, "program.bf");
+        assert_eq!(config.max_steps, Some(1000));
+        assert_eq!(config.timeout, Some(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn test_build_invalid_limit() {
+        assert!(Config::build(&args(&["brainfuck_rust", "program.bf", "--max-steps"])).is_err());
+        assert!(Config::build(&args(&[
+            "brainfuck_rust",
+            "--timeout",
+            "soon",
+            "program.bf"
+        ]))
+        .is_err());
+        assert!(Config::build(&args(&["brainfuck_rust", "--max-steps", "10"])).is_err());
+        assert!(Config::build(&args(&[
+            "brainfuck_rust",
+            "--max-tape-bytes",
+            "65536",
+            "program.bf"
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn test_build_growable_tape() {
+        let config = Config::build(&args(&[
+            "brainfuck_rust",
+            "--growable-tape",
+            "--max-tape-bytes",
+            "65536",
+            "program.bf",
+        ]))
+        .unwrap();
+
+        assert!(config.growable_tape);
+        assert_eq!(config.max_tape_bytes, Some(65536));
+    }
+
+    #[test]
+    fn test_build_flush_policy() {
+        let config = Config::build(&args(&["brainfuck_rust", "program.bf"])).unwrap();
+        assert_eq!(config.flush_policy, FlushPolicy::Newline);
+
+        let config =
+            Config::build(&args(&["brainfuck_rust", "--flush", "exit", "program.bf"])).unwrap();
+        assert_eq!(config.flush_policy, FlushPolicy::Exit);
+
+        assert!(
+            Config::build(&args(&["brainfuck_rust", "--flush", "never", "program.bf"])).is_err()
+        );
+    }
+
+    #[test]
+    fn test_build_fmt() {
+        let config = Config::build(&args(&["brainfuck_rust", "fmt", "program.bf"])).unwrap();
+        assert_eq!(config.command, Command::Fmt);
+        assert_eq!(config.format_width, DEFAULT_WIDTH);
+        assert!(!config.check_formatting);
+
+        let config = Config::build(&args(&[
+            "brainfuck_rust",
+            "fmt",
+            "--width",
+            "40",
+            "--check",
+            "program.bf",
+        ]))
+        .unwrap();
+        assert_eq!(config.format_width, 40);
+        assert!(config.check_formatting);
+
+        assert!(Config::build(&args(&[
+            "brainfuck_rust",
+            "fmt",
+            "--width",
+            "0",
+            "program.bf"
+        ]))
+        .is_err());
+
+        match Config::build(&args(&["brainfuck_rust", "lint", "--check", "program.bf"])) {
+            Err(ConfigError::CheckRequiresFmt) => {}
+            _ => panic!("--check should only be accepted by fmt"),
+        }
+    }
+
+    #[test]
+    fn test_build_error_reporting() {
+        let config = Config::build(&args(&["brainfuck_rust", "program.bf"])).unwrap();
+        assert_eq!(config.error_format, ErrorFormat::Human);
+        assert!(!config.exit_code_from_cell);
+
+        let config = Config::build(&args(&[
+            "brainfuck_rust",
+            "--error-format",
+            "json",
+            "--exit-code-from-cell",
+            "program.bf",
+        ]))
+        .unwrap();
+        assert_eq!(config.error_format, ErrorFormat::Json);
+        assert!(config.exit_code_from_cell);
+
+        assert!(Config::build(&args(&[
+            "brainfuck_rust",
+            "--error-format",
+            "xml",
+            "program.bf"
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn test_build_command() {
+        let config = Config::build(&args(&["brainfuck_rust", "compile", "program.bf"])).unwrap();
+        assert_eq!(config.command, Command::Compile);
+        assert_eq!(config.brainfuck_file_path, "program.bf");
+
+        let config = Config::build(&args(&["brainfuck_rust", "repl"])).unwrap();
+        assert_eq!(config.command, Command::Repl);
+
+        let config = Config::build(&args(&["brainfuck_rust", "--help"])).unwrap();
+        assert_eq!(config.command, Command::Help);
+
+        let config = Config::build(&args(&["brainfuck_rust", "-V"])).unwrap();
+        assert_eq!(config.command, Command::Version);
+
+        match Config::build(&args(&["brainfuck_rust", "check"])) {
+            Err(ConfigError::MissingProgram) => {}
+            _ => panic!("check without a file should be a MissingProgram error"),
+        }
+    }
+
+    #[test]
+    fn test_build_semantics() {
+        let config = Config::build(&args(&[
+            "brainfuck_rust",
+            "run",
+            "--tape-size",
+            "65536",
+            "--cell-width",
+            "16",
+            "--overflow",
+            "wrap",
+            "--eof",
+            "zero",
+            "--input",
+            "input.txt",
+            "--output",
+            "output.txt",
+            "program.bf",
+        ]))
+        .unwrap();
+
+        assert_eq!(config.tape_size, 65536);
+        assert_eq!(config.cell_width, CellWidth::Sixteen);
+        assert_eq!(config.overflow_mode, OverflowMode::Wrap);
+        assert_eq!(config.eof_policy, EofPolicy::Zero);
+        assert_eq!(config.input_file_path.as_deref(), Some("input.txt"));
+        assert_eq!(config.output_file_path.as_deref(), Some("output.txt"));
+    }
+
+    #[test]
+    fn test_build_invalid_arguments() {
+        match Config::build(&args(&[
+            "brainfuck_rust",
+            "--cell-width",
+            "12",
+            "program.bf",
+        ])) {
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "invalid value '12' for --cell-width (expected 8, 16 or 32)."
+            ),
+            Ok(_) => panic!("12 is not a valid cell width"),
+        }
+
+        match Config::build(&args(&["brainfuck_rust", "--fast", "program.bf"])) {
+            Err(ConfigError::UnknownFlag { flag }) => assert_eq!(flag, "--fast"),
+            _ => panic!("--fast is not a known flag"),
+        }
+
+        match Config::build(&args(&["brainfuck_rust", "a.bf", "b.bf"])) {
+            Err(ConfigError::UnexpectedArgument { argument }) => assert_eq!(argument, "b.bf"),
+            _ => panic!("only a single program can be run"),
+        }
+
+        assert!(Config::build(&args(&["brainfuck_rust", "--tape-size", "0", "a.bf"])).is_err());
+
+        match Config::build(&args(&["brainfuck_rust", "-tape-size", "10", "a.bf"])) {
+            Err(ConfigError::UnknownFlag { flag }) => assert_eq!(flag, "-tape-size"),
+            _ => panic!("settings need two dashes"),
+        }
+
+        // a program may be named like a setting
+        let config = Config::build(&args(&["brainfuck_rust", "run", "timeout"])).unwrap();
+        assert_eq!(config.brainfuck_file_path, "timeout");
+    }
+
+    #[test]
+    fn test_build_program_source() {
+        let config = Config::build(&args(&["brainfuck_rust", "-e", "++[>+<-]"])).unwrap();
+        assert_eq!(config.inline_program.as_deref(), Some("++[>+<-]"));
+        assert_eq!(config.program_name(), "<inline>");
+
+        let config = Config::build(&args(&["brainfuck_rust", "check", "-"])).unwrap();
+        assert_eq!(config.brainfuck_file_path, "-");
+        assert_eq!(config.program_name(), "<stdin>");
+
+        assert!(Config::build(&args(&["brainfuck_rust", "-e", "+", "program.bf"])).is_err());
+    }
+
+    #[test]
+    fn test_build_embedded_input() {
+        let config =
+            Config::build(&args(&["brainfuck_rust", "--embedded-input", "program.bf"])).unwrap();
+        assert!(config.embedded_input);
+
+        match Config::build(&args(&[
+            "brainfuck_rust",
+            "--embedded-input",
+            "--input",
+            "input.txt",
+            "program.bf",
+        ])) {
+            Err(ConfigError::ConflictingInput) => {}
+            _ => panic!("embedded input and an input file cannot be combined"),
+        }
+    }
+
+    #[test]
+    fn test_build_macros() {
+        let config = Config::build(&args(&[
+            "brainfuck_rust",
+            "assemble",
+            "--macros",
+            "program.bfm",
+        ]))
+        .unwrap();
+        assert_eq!(config.command, Command::Assemble);
+        assert!(config.macros);
+
+        match Config::build(&args(&[
+            "brainfuck_rust",
+            "--macros",
+            "--embedded-input",
+            "program.bfm",
+        ])) {
+            Err(ConfigError::ConflictingMacros) => {}
+            _ => panic!("macros and embedded input cannot be combined"),
+        }
+    }
+
+    #[test]
+    fn test_build_includes() {
+        let config = Config::build(&args(&["brainfuck_rust", "program.bf"])).unwrap();
+        assert!(!config.includes);
+
+        let config = Config::build(&args(&["brainfuck_rust", "--includes", "program.bf"])).unwrap();
+        assert!(config.includes);
+        let config = Config::build(&args(&[
+            "brainfuck_rust",
+            "--includes",
+            "--no-includes",
+            "program.bf",
+        ]))
+        .unwrap();
+        assert!(!config.includes);
+    }
+
+    #[test]
+    fn test_build_dialect() {
+        let config = Config::build(&args(&[
+            "brainfuck_rust",
+            "--eof",
+            "max",
+            "--dialect",
+            "beef",
+            "program.bf",
+        ]))
+        .unwrap();
+
+        assert_eq!(config.dialect, Some(Dialect::Beef));
+        assert!(config.growable_tape);
+        assert_eq!(config.overflow_mode, OverflowMode::Wrap);
+        // flags override the dialect regardless of their position
+        assert_eq!(config.eof_policy, EofPolicy::MaxValue);
+
+        let path = env::temp_dir().join(format!(
+            "brainfuck_rust_dialect_{}.toml",
+            std::process::id()
+        ));
+        fs::write(&path, "overflow = \"error\"\ndialect = \"original\"\n").unwrap();
+        let config = Config::build(&args(&[
+            "brainfuck_rust",
+            "--config",
+            path.to_str().unwrap(),
+            "program.bf",
+        ]))
+        .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.dialect, Some(Dialect::Original));
+        assert_eq!(config.overflow_mode, OverflowMode::Error);
+
+        assert!(
+            Config::build(&args(&["brainfuck_rust", "--dialect", "ook", "program.bf"])).is_err()
+        );
+    }
+
+    #[test]
+    fn test_build_config_file() {
+        let path =
+            env::temp_dir().join(format!("brainfuck_rust_config_{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            "tape-size = 65536\ncell_width = 16\neof = \"zero\"\ngrowable-tape = true\n",
+        )
+        .unwrap();
+        let config_path = path.to_str().unwrap();
+
+        let config = Config::build(&args(&[
+            "brainfuck_rust",
+            "--config",
+            config_path,
+            "--eof",
+            "max",
+            "program.bf",
+        ]))
+        .unwrap();
+
+        assert_eq!(config.tape_size, 65536);
+        assert_eq!(config.cell_width, CellWidth::Sixteen);
+        assert!(config.growable_tape);
+        // flags override the config file
+        assert_eq!(config.eof_policy, EofPolicy::MaxValue);
+
+        let config = Config::with_config_file(
+            &args(&["brainfuck_rust", "--no-growable-tape", "program.bf"]),
+            Some(&path),
+        )
+        .unwrap();
+        assert_eq!(config.tape_size, 65536);
+        assert!(!config.growable_tape);
+
+        let no_config = args(&["brainfuck_rust", "--no-config", "program.bf"]);
+        let config = Config::with_config_file(&no_config, Some(&path)).unwrap();
+        assert_eq!(config.tape_size, DEFAULT_TAPE_LENGTH);
+
+        fs::write(&path, "tape-size = 65536\ncolor = \"red\"\n").unwrap();
+        match Config::build(&args(&["brainfuck_rust", "--config", config_path, "a.bf"])) {
+            Err(ConfigError::InvalidConfigFile { message, .. }) => {
+                assert_eq!(message, "line 2: unknown setting color")
+            }
+            _ => panic!("color is not a known setting"),
+        }
+
+        fs::write(&path, "overflow = \"saturate\"\n").unwrap();
+        match Config::build(&args(&["brainfuck_rust", "--config", config_path, "a.bf"])) {
+            Err(ConfigError::InvalidConfigFile { message, .. }) => assert_eq!(
+                message,
+                "line 1: invalid value 'saturate' for overflow (expected error or wrap)."
+            ),
+            _ => panic!("saturate is not a valid overflow mode"),
+        }
 
-        Ok(Config {
-            brainfuck_file_path,
-        })
+        fs::remove_file(&path).unwrap();
     }
 }
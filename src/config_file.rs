@@ -0,0 +1,184 @@
+use custom_error::custom_error;
+use std::path::{Path, PathBuf};
+
+/// Name of the config file that is searched in the current directory and its ancestors
+pub const CONFIG_FILE_NAME: &str = "brainfuck.toml";
+
+custom_error! { pub ConfigFileError{line: usize, message: String} = "line {line}: {message}" }
+
+/// A `key = value` setting of a config file
+#[derive(Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// Key with underscores replaced by dashes, so it matches the command-line flag
+    pub key: String,
+    /// Value without quotes, e.g. "wrap", "65536" or "true"
+    pub value: String,
+    pub line: usize,
+}
+
+/// Returns the path of the nearest config file in dir or one of its ancestors
+///
+/// # Arguments
+///
+/// * `dir` - Directory in which the search starts
+pub fn find_config_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|ancestor| ancestor.join(CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Parses the subset of TOML used by config files: comments and top-level `key = value` pairs
+/// with string, integer, or boolean values
+///
+/// # Arguments
+///
+/// * `contents` - The contents of a config file
+pub fn parse(contents: &str) -> Result<Vec<Entry>, ConfigFileError> {
+    let mut entries = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |message: &str| ConfigFileError {
+            line: line_number,
+            message: message.to_string(),
+        };
+
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            return Err(error(
+                "tables are not supported, settings must be top-level",
+            ));
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| error("expected a setting of the form key = value"))?;
+        let key = key.trim();
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(error("invalid key"));
+        }
+
+        let value = parse_value(value.trim()).ok_or_else(|| error("invalid value"))?;
+
+        entries.push(Entry {
+            key: key.replace('_', "-"),
+            value,
+            line: line_number,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Parses a string, integer, or boolean value that may be followed by a comment
+fn parse_value(value: &str) -> Option<String> {
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut string = String::new();
+        let mut chars = rest.chars();
+        loop {
+            match chars.next()? {
+                '"' => break,
+                '\\' => match chars.next()? {
+                    '"' => string.push('"'),
+                    '\\' => string.push('\\'),
+                    _ => return None,
+                },
+                c => string.push(c),
+            }
+        }
+        return is_comment_or_empty(chars.as_str()).then_some(string);
+    }
+
+    let value = value
+        .split_once('#')
+        .map_or(value, |(value, _)| value)
+        .trim();
+    match value {
+        "true" | "false" => Some(value.to_string()),
+        _ if !value.is_empty() && value.chars().all(|c| c.is_ascii_digit() || c == '_') => {
+            Some(value.replace('_', ""))
+        }
+        _ => None,
+    }
+}
+
+fn is_comment_or_empty(rest: &str) -> bool {
+    let rest = rest.trim();
+    rest.is_empty() || rest.starts_with('#')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn entry(key: &str, value: &str, line: usize) -> Entry {
+        Entry {
+            key: key.to_string(),
+            value: value.to_string(),
+            line,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let contents = "\
+# settings shared by all programs
+tape_size = 65_536
+overflow = \"wrap\"   # 8-bit wrapping cells
+
+growable-tape = false
+";
+
+        assert_eq!(
+            parse(contents).unwrap(),
+            vec![
+                entry("tape-size", "65536", 2),
+                entry("overflow", "wrap", 3),
+                entry("growable-tape", "false", 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (contents, line) in [
+            ("[interpreter]\n", 1),
+            ("\ntape-size\n", 2),
+            ("overflow = wrap\n", 1),
+            ("overflow = \"wrap\n", 1),
+            ("overflow = \"wrap\" x\n", 1),
+            ("= 3\n", 1),
+        ] {
+            match parse(contents) {
+                Err(e) => assert_eq!(e.line, line, "{:?}", contents),
+                Ok(_) => panic!("{:?} should not be parsed", contents),
+            }
+        }
+    }
+
+    #[test]
+    fn test_find() {
+        let root = std::env::temp_dir().join(format!("brainfuck_rust_find_{}", std::process::id()));
+        let nested = root.join("a").join("b");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(CONFIG_FILE_NAME), "tape-size = 10\n").unwrap();
+
+        assert_eq!(find_config_file(&nested), Some(root.join(CONFIG_FILE_NAME)));
+
+        fs::write(nested.join(CONFIG_FILE_NAME), "tape-size = 20\n").unwrap();
+        assert_eq!(
+            find_config_file(&nested),
+            Some(nested.join(CONFIG_FILE_NAME))
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,566 @@
+use std::fmt;
+use std::io;
+
+use crate::assembler::MacroError;
+use crate::check::SyntaxError;
+use crate::interpreter::InterpreterError;
+use crate::preprocessor::{IncludeError, SourceFile};
+use crate::state::StateTransitionError;
+use crate::token::Span;
+use crate::Error;
+
+/// Format in which diagnostics are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorFormat {
+    /// rustc-style messages with source snippets
+    #[default]
+    Human,
+    /// One JSON object per line for editor integration
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// A source location with an explanation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A message about a program with an error code, severity, source locations, and notes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub code: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub primary: Option<Label>,
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn new(code: &'static str, severity: Severity, message: &str) -> Diagnostic {
+        Diagnostic {
+            code,
+            severity,
+            message: message.to_string(),
+            primary: None,
+            secondary: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_primary(mut self, span: Span, message: &str) -> Diagnostic {
+        self.primary = Some(Label {
+            span,
+            message: message.to_string(),
+        });
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: &str) -> Diagnostic {
+        self.secondary.push(Label {
+            span,
+            message: message.to_string(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Diagnostic {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    /// Creates a diagnostic for an error returned while checking or running a program
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error, runtime errors point at the failed instruction
+    pub fn from_error(error: &Error) -> Diagnostic {
+        let message = error.to_string();
+        match error {
+            Error::Syntax { source } => match source {
+                SyntaxError::UnmatchedOpening { span, end, .. } => {
+                    Diagnostic::new("E0001", Severity::Error, "unmatched '['")
+                        .with_primary(*span, "this loop is never closed")
+                        .with_secondary(*end, "expected ']' before the end of the program")
+                }
+                SyntaxError::UnmatchedClosing { span, .. } => {
+                    Diagnostic::new("E0002", Severity::Error, "unmatched ']'")
+                        .with_primary(*span, "there is no open loop to close")
+                }
+            }
+            .with_note("every '[' needs a matching ']'"),
+            Error::Macro { source } => {
+                let (code, message, label) = match source {
+                    MacroError::UnexpectedCharacter { character, .. } => (
+                        "E0501",
+                        format!("unexpected character '{}'", character),
+                        "not part of the macro language",
+                    ),
+                    MacroError::UnterminatedString { .. } => (
+                        "E0502",
+                        String::from("unterminated string"),
+                        "this string is never closed",
+                    ),
+                    MacroError::InvalidNumber { text, .. } => (
+                        "E0503",
+                        format!("invalid number '{}'", text),
+                        "expected a decimal integer",
+                    ),
+                    MacroError::NumberOutOfRange { text, max, .. } => (
+                        "E0510",
+                        format!("number '{}' is out of range", text),
+                        &*format!("the largest allowed number is {}", max),
+                    ),
+                    MacroError::Expected {
+                        expected, found, ..
+                    } => (
+                        "E0504",
+                        format!("expected {}, found {}", expected, found),
+                        "unexpected here",
+                    ),
+                    MacroError::UnknownStatement { name, .. } => (
+                        "E0505",
+                        format!("unknown statement or macro '{}'", name),
+                        "macros must be defined before they are used",
+                    ),
+                    MacroError::UnknownVariable { name, .. } => (
+                        "E0506",
+                        format!("unknown variable '{}'", name),
+                        "not declared with var",
+                    ),
+                    MacroError::DuplicateName { name, .. } => (
+                        "E0507",
+                        format!("'{}' is already defined", name),
+                        "defined again here",
+                    ),
+                    MacroError::NotAtTopLevel { .. } => (
+                        "E0508",
+                        String::from("declaration inside a block"),
+                        "move this declaration out of the block",
+                    ),
+                    MacroError::RecursiveMacro { name, .. } => (
+                        "E0509",
+                        format!("macro '{}' calls itself", name),
+                        "macros are expanded in place and cannot recurse",
+                    ),
+                };
+                Diagnostic::new(code, Severity::Error, &message).with_primary(source.span(), label)
+            }
+            Error::Include { source } => match source {
+                IncludeError::MissingPath { span } => {
+                    Diagnostic::new("E0004", Severity::Error, "missing path in #include")
+                        .with_primary(*span, "expected a path in double quotes after this")
+                }
+                IncludeError::Cycle { path, span } => Diagnostic::new(
+                    "E0005",
+                    Severity::Error,
+                    &format!("'{}' includes itself", path),
+                )
+                .with_primary(*span, "this file is already being included")
+                .with_note("included files cannot include the files that include them"),
+                IncludeError::TrailingCommands { span } => {
+                    Diagnostic::new("E0006", Severity::Error, "commands after #include")
+                        .with_primary(*span, "these commands would be dropped with the directive")
+                        .with_note("move the commands to a line of their own")
+                }
+                IncludeError::Unreadable { path, span, source } => Diagnostic::new(
+                    "E0302",
+                    Severity::Error,
+                    &format!("cannot include '{}'", path),
+                )
+                .with_primary(*span, "included here")
+                .with_note(&format!(
+                    "{}, paths are relative to the including file",
+                    source
+                )),
+            },
+            Error::WithFiles { source } => Diagnostic::from_error(&source.error),
+            Error::Runtime { source } => {
+                let mut diagnostic = Diagnostic::from_error(&source.error);
+                if source.span.line > 0 {
+                    let label = match source.error.root() {
+                        Error::Interpreter {
+                            source: InterpreterError::InfiniteLoop { .. },
+                        } => "this loop never ends",
+                        _ => "the program failed here",
+                    };
+                    diagnostic = diagnostic.with_primary(source.span, label);
+                }
+                diagnostic.with_note(&format!(
+                    "failed at instruction {} after {} steps, pointer: {}, cell value: {}",
+                    source.token_index, source.steps, source.pointer, source.cell
+                ))
+            }
+            Error::StateTransition { source } => {
+                let (code, note) = match source {
+                    StateTransitionError::PointerUnderflow => {
+                        ("E0101", "the pointer cannot move left of cell 0")
+                    }
+                    StateTransitionError::PointerOverflow => (
+                        "E0102",
+                        "use --tape-size or --growable-tape to run the program on a larger tape",
+                    ),
+                    StateTransitionError::CellUnderflow => (
+                        "E0103",
+                        "use --overflow wrap to let cells wrap around below 0",
+                    ),
+                    StateTransitionError::CellOverflow => (
+                        "E0104",
+                        "use --overflow wrap or a larger --cell-width for values above the maximum",
+                    ),
+                    StateTransitionError::MemoryLimitExceeded { .. } => {
+                        ("E0105", "use --max-tape-bytes to allow a larger tape")
+                    }
+                };
+                Diagnostic::new(code, Severity::Error, &message).with_note(note)
+            }
+            Error::Interpreter { source } => {
+                let code = match source {
+                    InterpreterError::StepLimitExceeded { .. } => "E0203",
+                    InterpreterError::Timeout { .. } => "E0204",
+                    InterpreterError::Cancelled { .. } => "E0205",
+                    InterpreterError::EndOfInput => "E0206",
+                    InterpreterError::InfiniteLoop { .. } => "E0207",
+                };
+                let diagnostic = Diagnostic::new(code, Severity::Error, &message);
+                match source {
+                    InterpreterError::InfiniteLoop { .. } => diagnostic.with_note(
+                        "every iteration ends with the pointer and cells it started with",
+                    ),
+                    _ => diagnostic,
+                }
+            }
+            // programs are the only text read by the interpreter, input is read as bytes
+            Error::Io { source } if source.kind() == io::ErrorKind::InvalidData => {
+                Diagnostic::new("E0003", Severity::Error, "the program is not valid UTF-8")
+                    .with_note("brainfuck sources must be UTF-8 encoded")
+            }
+            Error::Io { .. } => Diagnostic::new("E0301", Severity::Error, &message),
+            Error::Config { .. } => Diagnostic::new("E0401", Severity::Error, &message)
+                .with_note("run with --help to see the usage"),
+            Error::Options { .. } => Diagnostic::new("E0402", Severity::Error, &message),
+        }
+    }
+
+    /// Renders the diagnostic in the style of rustc with snippets of the affected source lines
+    ///
+    /// # Arguments
+    ///
+    /// * `file_name` - Name of the file of the first label shown in the location
+    /// * `source` - Source of that file, snippets are omitted if it is None
+    pub fn render(&self, file_name: &str, source: Option<&str>) -> String {
+        let mut rendered = format!("{}[{}]: {}\n", self.severity, self.code, self.message);
+
+        let mut labels: Vec<(&Label, bool)> = self.primary.iter().map(|l| (l, true)).collect();
+        labels.extend(self.secondary.iter().map(|l| (l, false)));
+        // the diagnostic is rendered in the file of its first label, labels in other files of
+        // the program are left out
+        if let Some(file) = labels.first().map(|(label, _)| label.span.file) {
+            labels.retain(|(label, _)| label.span.file == file);
+        }
+
+        let gutter = labels
+            .iter()
+            .map(|(label, _)| label.span.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let empty_gutter = format!("{} |", " ".repeat(gutter));
+
+        if let Some((label, _)) = labels.first() {
+            // spans of tokens built without a position only point at the file
+            let location = match label.span.line {
+                0 => file_name.to_string(),
+                line => format!("{}:{}:{}", file_name, line, label.span.column),
+            };
+            rendered.push_str(&format!("{}--> {}\n", " ".repeat(gutter), location));
+        }
+
+        if let Some(source) = source {
+            // spans of tokens built without a position have line 0 and get no snippet
+            let mut lines: Vec<usize> = labels
+                .iter()
+                .map(|(label, _)| label.span.line)
+                .filter(|line| *line > 0)
+                .collect();
+            lines.sort_unstable();
+            lines.dedup();
+
+            if !lines.is_empty() {
+                rendered.push_str(&format!("{}\n", empty_gutter));
+            }
+            for line in lines {
+                let text = source
+                    .lines()
+                    .nth(line - 1)
+                    .unwrap_or("")
+                    .replace('\t', " ");
+                let snippet = format!("{:>gutter$} | {}", line, text, gutter = gutter);
+                rendered.push_str(snippet.trim_end());
+                rendered.push('\n');
+
+                let mut line_labels: Vec<&(&Label, bool)> = labels
+                    .iter()
+                    .filter(|(label, _)| label.span.line == line)
+                    .collect();
+                line_labels.sort_by_key(|(label, _)| label.span.column);
+
+                for (label, primary) in line_labels {
+                    let width = source
+                        .get(label.span.start..label.span.end)
+                        .map_or(1, |text| text.chars().count().max(1));
+                    let marker = if *primary { "^" } else { "-" };
+                    rendered.push_str(&format!(
+                        "{} {}{} {}\n",
+                        empty_gutter,
+                        " ".repeat(label.span.column.saturating_sub(1)),
+                        marker.repeat(width),
+                        label.message
+                    ));
+                }
+            }
+        }
+
+        for note in &self.notes {
+            rendered.push_str(&format!("{} = note: {}\n", " ".repeat(gutter), note));
+        }
+
+        rendered
+    }
+
+    /// Renders the diagnostic as a single-line JSON object, every label names the file it points
+    /// into
+    ///
+    /// # Arguments
+    ///
+    /// * `file_name` - Name of the program
+    /// * `files` - Files of a program with includes that the spans refer to, empty otherwise
+    pub fn to_json(&self, file_name: &str, files: &[SourceFile]) -> String {
+        let label_file = |span: &Span| match (span.file, files.get(span.file)) {
+            (0, _) | (_, None) => file_name.to_string(),
+            (_, Some(file)) => file.path.display().to_string(),
+        };
+        let labels: Vec<String> = self
+            .primary
+            .iter()
+            .map(|label| (label, true))
+            .chain(self.secondary.iter().map(|label| (label, false)))
+            .map(|(label, primary)| {
+                format!(
+                    "{{\"primary\":{},\"message\":{},\"file\":{},\"start\":{},\"end\":{},\"line\":{},\"column\":{}}}",
+                    primary,
+                    json_string(&label.message),
+                    json_string(&label_file(&label.span)),
+                    label.span.start,
+                    label.span.end,
+                    label.span.line,
+                    label.span.column
+                )
+            })
+            .collect();
+        let notes: Vec<String> = self.notes.iter().map(|note| json_string(note)).collect();
+
+        format!(
+            "{{\"code\":{},\"severity\":{},\"message\":{},\"file\":{},\"labels\":[{}],\"notes\":[{}]}}",
+            json_string(self.code),
+            json_string(&self.severity.to_string()),
+            json_string(&self.message),
+            json_string(file_name),
+            labels.join(","),
+            notes.join(",")
+        )
+    }
+}
+
+/// Quotes and escapes a string for JSON
+fn json_string(string: &str) -> String {
+    let mut json = String::from("\"");
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::check_brackets;
+    use crate::lex;
+
+    #[test]
+    fn test_render_unmatched_opening() {
+        let source = "+++\n+[->[-]<\n";
+        let error = check_brackets(&lex(source)).unwrap_err();
+        let diagnostic = Diagnostic::from_error(&error.into());
+
+        assert_eq!(
+            diagnostic.render("program.bf", Some(source)),
+            "\
+error[E0001]: unmatched '['
+ --> program.bf:2:2
+  |
+2 | +[->[-]<
+  |  ^ this loop is never closed
+3 |
+  | - expected ']' before the end of the program
+  = note: every '[' needs a matching ']'
+"
+        );
+    }
+
+    #[test]
+    fn test_render_without_source() {
+        let error = check_brackets(&lex("+]")).unwrap_err();
+        let diagnostic = Diagnostic::from_error(&error.into());
+
+        assert_eq!(
+            diagnostic.render("<stdin>", None),
+            "\
+error[E0002]: unmatched ']'
+ --> <stdin>:1:2
+  = note: every '[' needs a matching ']'
+"
+        );
+    }
+
+    #[test]
+    fn test_render_span_without_position() {
+        let diagnostic = Diagnostic::new("E0001", Severity::Error, "unmatched '['")
+            .with_primary(Span::default(), "this loop is never closed");
+
+        assert_eq!(
+            diagnostic.render("program.bf", Some("[")),
+            "\
+error[E0001]: unmatched '['
+ --> program.bf
+"
+        );
+    }
+
+    #[test]
+    fn test_runtime_error() {
+        let error = StateTransitionError::PointerUnderflow;
+        let diagnostic = Diagnostic::from_error(&error.into());
+
+        assert_eq!(diagnostic.code, "E0101");
+        assert_eq!(
+            diagnostic.render("program.bf", Some("<")),
+            "\
+error[E0101]: pointer underflow (state.pointer < 0)
+  = note: the pointer cannot move left of cell 0
+"
+        );
+    }
+
+    #[test]
+    fn test_runtime_error_context() {
+        let source = "+\n>>\n<<<";
+        let config = crate::Config {
+            inline_program: Some(source.to_string()),
+            ..Default::default()
+        };
+        let error = crate::run(config).unwrap_err();
+        let diagnostic = Diagnostic::from_error(&error);
+
+        assert_eq!(
+            diagnostic.render("program.bf", Some(source)),
+            "\
+error[E0101]: pointer underflow (state.pointer < 0)
+ --> program.bf:3:3
+  |
+3 | <<<
+  |   ^ the program failed here
+  = note: the pointer cannot move left of cell 0
+  = note: failed at instruction 5 after 6 steps, pointer: 0, cell value: 1
+"
+        );
+    }
+
+    #[test]
+    fn test_infinite_loop() {
+        let source = "+>+\n[<-+>]";
+        let config = crate::Config {
+            inline_program: Some(source.to_string()),
+            detect_infinite_loops: true,
+            ..Default::default()
+        };
+        let error = crate::run(config).unwrap_err();
+        let diagnostic = Diagnostic::from_error(&error);
+
+        assert_eq!(diagnostic.code, "E0207");
+        assert!(diagnostic
+            .render("program.bf", Some(source))
+            .contains("2 | [<-+>]\n  | ^ this loop never ends\n"));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let diagnostic = Diagnostic::new("E0002", Severity::Error, "unmatched ']'")
+            .with_primary(
+                Span {
+                    start: 1,
+                    end: 2,
+                    line: 1,
+                    column: 2,
+                    file: 0,
+                },
+                "there is no \"open\" loop",
+            )
+            .with_note("a note");
+
+        assert_eq!(
+            diagnostic.to_json("dir\\program.bf", &[]),
+            "{\"code\":\"E0002\",\"severity\":\"error\",\"message\":\"unmatched ']'\",\
+\"file\":\"dir\\\\program.bf\",\"labels\":[{\"primary\":true,\
+\"message\":\"there is no \\\"open\\\" loop\",\"file\":\"dir\\\\program.bf\",\
+\"start\":1,\"end\":2,\"line\":1,\"column\":2}],\"notes\":[\"a note\"]}"
+        );
+    }
+
+    #[test]
+    fn test_to_json_with_files() {
+        let span = |file| Span {
+            line: 1,
+            column: 1,
+            file,
+            ..Default::default()
+        };
+        let diagnostic = Diagnostic::new("E0001", Severity::Error, "unmatched '['")
+            .with_primary(span(1), "opened here")
+            .with_secondary(span(0), "included here");
+        let files = ["main.bf", "lib/loop.bf"].map(|path| SourceFile {
+            path: path.into(),
+            source: String::new(),
+        });
+
+        let json = diagnostic.to_json("main.bf", &files);
+        assert!(json.contains("\"message\":\"opened here\",\"file\":\"lib/loop.bf\""));
+        assert!(json.contains("\"message\":\"included here\",\"file\":\"main.bf\""));
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt;
+
+use crate::interpreter::EofPolicy;
+use crate::options::InterpreterOptions;
+use crate::state::{BoundsMode, CellWidth, OverflowMode, DEFAULT_TAPE_LENGTH};
+
+/// Semantics of a well-known brainfuck implementation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// Urban Müller's original compiler: 30000 wrapping 8-bit cells, ',' leaves the cell
+    /// unchanged at the end of the input
+    Original,
+    /// Oleg Mazonka's bff: a tape that grows to the right and wrapping 8-bit cells
+    Bff,
+    /// The conventions for portable programs: 30000 8-bit cells that must not wrap, ',' sets the
+    /// cell to 0 at the end of the input
+    Portable,
+    /// The beef interpreter: a tape that grows to the right and wrapping 8-bit cells, ',' sets
+    /// the cell to 0 at the end of the input
+    Beef,
+}
+
+impl Dialect {
+    pub fn from_name(name: &str) -> Option<Dialect> {
+        match name {
+            "original" => Some(Dialect::Original),
+            "bff" => Some(Dialect::Bff),
+            "portable" => Some(Dialect::Portable),
+            "beef" => Some(Dialect::Beef),
+            _ => None,
+        }
+    }
+
+    /// Returns interpreter options with the tape and cell semantics of the dialect
+    pub fn options(&self) -> InterpreterOptions {
+        let options = InterpreterOptions::new()
+            .tape_length(DEFAULT_TAPE_LENGTH)
+            .cell_width(CellWidth::Eight);
+
+        match self {
+            Dialect::Original => options
+                .overflow_mode(OverflowMode::Wrap)
+                .eof_policy(EofPolicy::Unchanged),
+            Dialect::Bff => options
+                .bounds_mode(BoundsMode::Grow)
+                .overflow_mode(OverflowMode::Wrap)
+                .eof_policy(EofPolicy::Unchanged),
+            Dialect::Portable => options
+                .overflow_mode(OverflowMode::Error)
+                .eof_policy(EofPolicy::Zero),
+            Dialect::Beef => options
+                .bounds_mode(BoundsMode::Grow)
+                .overflow_mode(OverflowMode::Wrap)
+                .eof_policy(EofPolicy::Zero),
+        }
+    }
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Dialect::Original => "original",
+            Dialect::Bff => "bff",
+            Dialect::Portable => "portable",
+            Dialect::Beef => "beef",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names() {
+        for dialect in [
+            Dialect::Original,
+            Dialect::Bff,
+            Dialect::Portable,
+            Dialect::Beef,
+        ] {
+            assert_eq!(Dialect::from_name(&dialect.to_string()), Some(dialect));
+        }
+        assert_eq!(Dialect::from_name("ook"), None);
+    }
+
+    #[test]
+    fn test_options_are_valid() {
+        for dialect in [
+            Dialect::Original,
+            Dialect::Bff,
+            Dialect::Portable,
+            Dialect::Beef,
+        ] {
+            assert!(dialect.options().validate().is_ok(), "{}", dialect);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use custom_error::custom_error;
+use std::fmt;
+use std::io;
+
+use crate::assembler::MacroError;
+use crate::check::SyntaxError;
+use crate::config::ConfigError;
+use crate::interpreter::{InterpreterError, RuntimeError};
+use crate::options::OptionsError;
+use crate::preprocessor::{IncludeError, SourceFile};
+use crate::state::StateTransitionError;
+
+custom_error! {
+    /// Error returned by the library
+    ///
+    /// Errors raised while a program is executed are wrapped in `Runtime` together with the failed
+    /// instruction, use `root` to get the underlying error. Errors in a program with included
+    /// files are wrapped in `WithFiles` together with the files their spans point into.
+    #[non_exhaustive]
+    pub Error
+    Io{source: io::Error} = "{source}",
+    Config{source: ConfigError} = "{source}",
+    Options{source: OptionsError} = "{source}",
+    Syntax{source: SyntaxError} = "{source}",
+    Macro{source: MacroError} = "{source}",
+    Include{source: IncludeError} = "{source}",
+    StateTransition{source: StateTransitionError} = "{source}",
+    Interpreter{source: InterpreterError} = "{source}",
+    Runtime{source: RuntimeError} = "{source}",
+    WithFiles{source: FilesError} = "{source}"
+}
+
+/// An error in a program with included files together with the files its spans point into
+#[derive(Debug)]
+pub struct FilesError {
+    pub error: Box<Error>,
+    /// Files of the program, the file of a span is an index into them
+    pub files: Vec<SourceFile>,
+}
+
+impl fmt::Display for FilesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for FilesError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+impl Error {
+    /// Returns the underlying error of a runtime error, or the error itself
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Runtime { source } => source.error.root(),
+            Error::WithFiles { source } => source.error.root(),
+            error => error,
+        }
+    }
+
+    /// Returns the files of a program with includes that the spans of the error point into, the
+    /// file of a span is an index into them
+    pub fn files(&self) -> &[SourceFile] {
+        match self {
+            Error::WithFiles { source } => &source.files,
+            _ => &[],
+        }
+    }
+
+    /// Attaches the files of a program to the error if it included other files
+    pub(crate) fn with_files(self, files: &[SourceFile]) -> Error {
+        if files.len() > 1 {
+            Error::WithFiles {
+                source: FilesError {
+                    error: Box::new(self),
+                    files: files.to_vec(),
+                },
+            }
+        } else {
+            self
+        }
+    }
+
+    /// Returns whether the error is a step limit, memory limit, timeout, or cancellation
+    pub fn is_limit(&self) -> bool {
+        matches!(
+            self.root(),
+            Error::StateTransition {
+                source: StateTransitionError::MemoryLimitExceeded { .. }
+            } | Error::Interpreter {
+                source: InterpreterError::StepLimitExceeded { .. }
+                    | InterpreterError::Timeout { .. }
+                    | InterpreterError::Cancelled { .. }
+            }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{run, Config};
+    use std::error::Error as _;
+
+    /// Runs a program given inline with the given config
+    fn run_inline(program_string: &str, config: Config) -> Result<Vec<u8>, Error> {
+        run(Config {
+            inline_program: Some(program_string.to_string()),
+            ..config
+        })
+    }
+
+    #[test]
+    fn test_root_and_source() {
+        let error = run_inline("<", Config::default()).unwrap_err();
+
+        assert!(matches!(error, Error::Runtime { .. }));
+        assert!(matches!(
+            error.root(),
+            Error::StateTransition {
+                source: StateTransitionError::PointerUnderflow
+            }
+        ));
+        assert!(!error.is_limit());
+
+        // the chain leads from the runtime error over the state transition error to its cause
+        let runtime_error = error.source().unwrap();
+        assert!(runtime_error.is::<RuntimeError>());
+        let state_transition_error = runtime_error.source().unwrap().source().unwrap();
+        assert!(state_transition_error.is::<StateTransitionError>());
+    }
+
+    #[test]
+    fn test_with_files_source() {
+        let config = Config {
+            includes: true,
+            ..Default::default()
+        };
+        let error = run_inline("#include \"tests/lib/newline.bf\"\n]", config).unwrap_err();
+
+        assert_eq!(error.files().len(), 2);
+        assert!(matches!(error.root(), Error::Syntax { .. }));
+        let files_error = error.source().unwrap();
+        assert!(files_error.is::<FilesError>());
+        assert!(files_error.source().unwrap().is::<Error>());
+        assert_eq!(error.to_string(), error.root().to_string());
+    }
+
+    #[test]
+    fn test_is_limit() {
+        let config = Config {
+            max_steps: Some(10),
+            ..Default::default()
+        };
+        assert!(run_inline("+[]", config).unwrap_err().is_limit());
+        assert!(!run_inline("[", Config::default()).unwrap_err().is_limit());
+    }
+}
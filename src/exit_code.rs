@@ -0,0 +1,107 @@
+use crate::interpreter::InterpreterError;
+use crate::preprocessor::IncludeError;
+use crate::Error;
+
+/// Exit code of the command-line interface for each category of failure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    Success = 0,
+    /// Errors that fit no other category
+    Failure = 1,
+    /// Invalid command-line arguments, config file, or interpreter options
+    Usage = 2,
+    /// A file could not be read, written, or included
+    Io = 3,
+    /// Unmatched brackets, an invalid include directive, or an invalid program in the macro
+    /// language
+    Syntax = 4,
+    /// Pointer or cell out of range, or reading beyond the input with --eof error
+    RuntimeFault = 5,
+    /// Step limit or tape memory limit exceeded
+    ResourceLimit = 6,
+    /// Timeout exceeded or execution cancelled
+    Timeout = 7,
+    /// lint or bounds reported warnings, or fmt --check found an unformatted program
+    Findings = 8,
+}
+
+impl ExitCode {
+    /// Returns the exit code for an error returned by the library
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error, runtime errors are categorized by the error they wrap
+    pub fn from_error(error: &Error) -> ExitCode {
+        if error.is_limit() {
+            return match error.root() {
+                Error::Interpreter {
+                    source: InterpreterError::Timeout { .. } | InterpreterError::Cancelled { .. },
+                } => ExitCode::Timeout,
+                _ => ExitCode::ResourceLimit,
+            };
+        }
+
+        match error.root() {
+            Error::Config { .. } | Error::Options { .. } => ExitCode::Usage,
+            Error::Io { .. }
+            | Error::Include {
+                source: IncludeError::Unreadable { .. },
+            } => ExitCode::Io,
+            Error::Syntax { .. } | Error::Macro { .. } | Error::Include { .. } => ExitCode::Syntax,
+            Error::StateTransition { .. } | Error::Interpreter { .. } => ExitCode::RuntimeFault,
+            // root never returns a wrapped error
+            Error::Runtime { .. } | Error::WithFiles { .. } => ExitCode::Failure,
+        }
+    }
+
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{run, Config};
+
+    fn exit_code(program_string: &str, config: Config) -> ExitCode {
+        let config = Config {
+            inline_program: Some(program_string.to_string()),
+            ..config
+        };
+        let error = run(config).unwrap_err();
+        ExitCode::from_error(&error)
+    }
+
+    #[test]
+    fn test_from_error() {
+        assert_eq!(exit_code("[", Config::default()), ExitCode::Syntax);
+        assert_eq!(exit_code("<", Config::default()), ExitCode::RuntimeFault);
+        assert_eq!(exit_code("-", Config::default()), ExitCode::RuntimeFault);
+
+        let config = Config {
+            max_steps: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(exit_code("+[]", config), ExitCode::ResourceLimit);
+
+        let config = Config {
+            growable_tape: true,
+            tape_size: 1,
+            max_tape_bytes: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(exit_code(">>>>>", config), ExitCode::ResourceLimit);
+
+        let config = Config {
+            timeout: Some(std::time::Duration::ZERO),
+            ..Default::default()
+        };
+        assert_eq!(exit_code("+[]", config), ExitCode::Timeout);
+
+        let error = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+        assert_eq!(ExitCode::from_error(&error.into()), ExitCode::Io);
+        let error = crate::ConfigError::MissingProgram;
+        assert_eq!(ExitCode::from_error(&error.into()), ExitCode::Usage);
+    }
+}
@@ -0,0 +1,265 @@
+use crate::token::{Token, TokenValue};
+
+/// Number of spaces per loop nesting level
+const INDENT: usize = 2;
+
+/// Default maximum line length of formatted code
+pub const DEFAULT_WIDTH: usize = 80;
+
+/// A unit of formatted output
+enum Item<'a> {
+    Command(char),
+    /// A loop without nested loops or comments, kept on a single line if it fits
+    InlineLoop(String),
+    Comment(&'a str),
+    BlankLine,
+}
+
+/// Formats a brainfuck program: loops are indented by their nesting depth, runs of commands are
+/// wrapped at width, and comments are kept on their own lines
+///
+/// The brackets of the program must match.
+///
+/// # Arguments
+///
+/// * `source` - The source of the program
+/// * `tokens` - The tokens lexed from source
+/// * `width` - Maximum line length of code, longer comments are not wrapped
+pub fn format(source: &str, tokens: &[Token], width: usize) -> String {
+    let items = items(source, tokens, width);
+    let mut formatter = Formatter {
+        lines: Vec::new(),
+        current: String::new(),
+        depth: 0,
+        width,
+    };
+
+    for item in items {
+        match item {
+            Item::Command('[') => {
+                formatter.flush();
+                formatter.push_line("[");
+                formatter.depth += 1;
+            }
+            Item::Command(']') => {
+                formatter.flush();
+                formatter.depth = formatter.depth.saturating_sub(1);
+                formatter.push_line("]");
+            }
+            Item::Command(c) => formatter.push_code(&c.to_string()),
+            Item::InlineLoop(code) => formatter.push_code(&code),
+            Item::Comment(text) => {
+                formatter.flush();
+                formatter.push_line(text);
+            }
+            Item::BlankLine => {
+                formatter.flush();
+                if formatter.lines.last().is_some_and(|line| !line.is_empty()) {
+                    formatter.lines.push(String::new());
+                }
+            }
+        }
+    }
+    formatter.flush();
+
+    while formatter.lines.last().is_some_and(|line| line.is_empty()) {
+        formatter.lines.pop();
+    }
+    formatter
+        .lines
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+struct Formatter {
+    lines: Vec<String>,
+    /// Code of the line that is being filled
+    current: String,
+    depth: usize,
+    width: usize,
+}
+
+impl Formatter {
+    fn indentation(&self) -> String {
+        " ".repeat(self.depth * INDENT)
+    }
+
+    fn push_line(&mut self, text: &str) {
+        let line = format!("{}{}", self.indentation(), text);
+        self.lines.push(line);
+    }
+
+    /// Appends code to the current line and starts a new line if it would become too long
+    fn push_code(&mut self, code: &str) {
+        let length = self.depth * INDENT + self.current.len() + code.len();
+        if !self.current.is_empty() && length > self.width {
+            self.flush();
+        }
+        self.current.push_str(code);
+    }
+
+    fn flush(&mut self) {
+        if !self.current.is_empty() {
+            let code = std::mem::take(&mut self.current);
+            self.push_line(&code);
+        }
+    }
+}
+
+/// Splits the source into commands, inline loops, comments, and blank lines
+fn items<'a>(source: &'a str, tokens: &[Token], width: usize) -> Vec<Item<'a>> {
+    let commands: Vec<&Token> = tokens
+        .iter()
+        .filter(|token| token.value != TokenValue::End)
+        .collect();
+
+    let mut items = Vec::new();
+    let mut position = 0;
+    let mut index = 0;
+    let mut depth = 0;
+
+    while index < commands.len() {
+        let token = commands[index];
+        comments(&source[position..token.span.start], &mut items);
+        position = token.span.end;
+
+        if token.value == TokenValue::JumpForwardIfZero {
+            if let Some((code, end)) = inline_loop(source, &commands[index..]) {
+                // the loop has to fit into a line of its own at the current depth
+                if depth * INDENT + code.len() <= width {
+                    position = commands[index + end].span.end;
+                    index += end + 1;
+                    items.push(Item::InlineLoop(code));
+                    continue;
+                }
+            }
+            depth += 1;
+        } else if token.value == TokenValue::JumpBackwardIfNonZero {
+            depth -= 1;
+        }
+
+        items.push(Item::Command(token.value.to_char().unwrap_or(' ')));
+        index += 1;
+    }
+    comments(&source[position..], &mut items);
+
+    items
+}
+
+/// Returns the code of a loop starting at the first command and the index of its closing bracket
+/// if it contains neither loops nor comments
+fn inline_loop(source: &str, commands: &[&Token]) -> Option<(String, usize)> {
+    let mut code = String::from("[");
+
+    for (index, window) in commands.windows(2).enumerate() {
+        let between = &source[window[0].span.end..window[1].span.start];
+        if !between.trim().is_empty() {
+            return None;
+        }
+
+        match window[1].value {
+            TokenValue::JumpForwardIfZero => return None,
+            TokenValue::JumpBackwardIfNonZero => {
+                code.push(']');
+                return Some((code, index + 1));
+            }
+            ref value => code.push(value.to_char().unwrap_or(' ')),
+        }
+    }
+
+    None
+}
+
+/// Adds the comments of the text between two commands, empty lines between them are kept as
+/// BlankLine
+fn comments<'a>(text: &'a str, items: &mut Vec<Item<'a>>) {
+    let parts: Vec<&str> = text.split('\n').collect();
+
+    for (index, part) in parts.iter().enumerate() {
+        let comment = part.trim();
+        if !comment.is_empty() {
+            items.push(Item::Comment(comment));
+        } else if index > 0 && index < parts.len() - 1 {
+            items.push(Item::BlankLine);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex;
+
+    fn format_source(source: &str, width: usize) -> String {
+        format(source, &lex(source), width)
+    }
+
+    #[test]
+    fn test_indentation() {
+        let source = "++++++[>++++++++[>+<-]<-]>>.[-]";
+        assert_eq!(
+            format_source(source, DEFAULT_WIDTH),
+            "\
+++++++
+[
+  >++++++++[>+<-]<-
+]
+>>.[-]
+"
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        let source = "set cell 0 to 2: ++\n\n\n\n  loop: [ move to cell 1 > + < - ]   \n print .";
+        assert_eq!(
+            format_source(source, DEFAULT_WIDTH),
+            "\
+set cell 0 to 2:
+++
+
+loop:
+[
+  move to cell 1
+  >+<-
+]
+print
+.
+"
+        );
+    }
+
+    #[test]
+    fn test_wrapping() {
+        let source = "[>++++++++++<-]+++++++++++";
+        assert_eq!(
+            format_source(source, 8),
+            "[\n  >+++++\n  +++++<\n  -\n]\n++++++++\n+++\n"
+        );
+    }
+
+    #[test]
+    fn test_idempotent() {
+        let source = "\
+hello world [
+this is a comment loop
+]
+++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.
+
+trailing comment";
+
+        for width in [10, 20, DEFAULT_WIDTH] {
+            let formatted = format_source(source, width);
+            assert_eq!(format_source(&formatted, width), formatted);
+            assert_eq!(commands(&formatted), commands(source));
+        }
+    }
+
+    fn commands(source: &str) -> Vec<Option<char>> {
+        lex(source)
+            .iter()
+            .map(|token| token.value.to_char())
+            .collect()
+    }
+}
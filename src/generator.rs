@@ -0,0 +1,127 @@
+/// Loop counts tried for the multiplication loop that initializes the cells
+const LOOP_COUNTS: std::ops::RangeInclusive<u32> = 2..=16;
+
+/// Generates a brainfuck program that prints the given bytes
+///
+/// A multiplication loop sets up cells near the values of the bytes, and every byte is printed
+/// from the cell that is cheapest to reach and adjust. Cells keep their values, so repeated and
+/// similar bytes are cheap. Several loop counts and cell orders are tried and the shortest
+/// program is returned. The program neither moves left of cell 0 nor relies on cells wrapping
+/// around, so it runs with any cell width and overflow mode.
+///
+/// # Arguments
+///
+/// * `text` - The bytes the program prints
+pub fn text_to_brainfuck(text: &[u8]) -> String {
+    let mut shortest = print_from_cells(text, String::new(), vec![0]);
+
+    for count in LOOP_COUNTS {
+        let mut bases: Vec<u32> = Vec::new();
+        for &byte in text {
+            // cells must not exceed 255, the largest value of 8-bit cells
+            let base = ((byte as u32 + count / 2) / count).min(255 / count);
+            if base > 0 && !bases.contains(&base) {
+                bases.push(base);
+            }
+        }
+
+        let mut sorted = bases.clone();
+        sorted.sort_unstable();
+
+        // cells in the order of their first use and in ascending order
+        for bases in [bases, sorted] {
+            let (setup, cells) = multiplication_loop(count, &bases);
+            let program = print_from_cells(text, setup, cells);
+            if program.len() < shortest.len() {
+                shortest = program;
+            }
+        }
+    }
+
+    shortest
+}
+
+/// Returns a loop that runs count times and adds a base to each cell right of cell 0 in every
+/// iteration, and the values of the cells after the loop
+fn multiplication_loop(count: u32, bases: &[u32]) -> (String, Vec<u32>) {
+    let mut setup = "+".repeat(count as usize);
+    setup.push('[');
+    for base in bases {
+        setup.push('>');
+        setup.push_str(&"+".repeat(*base as usize));
+    }
+    setup.push_str(&"<".repeat(bases.len()));
+    setup.push_str("-]");
+
+    let mut cells = vec![0];
+    cells.extend(bases.iter().map(|base| base * count));
+    (setup, cells)
+}
+
+/// Appends the code that prints the text to setup, starting on cell 0 with the given cell values
+fn print_from_cells(text: &[u8], mut program: String, mut cells: Vec<u32>) -> String {
+    let mut pointer: usize = 0;
+
+    for &byte in text {
+        let target = byte as u32;
+        let cost = |cell: usize| pointer.abs_diff(cell) + cells[cell].abs_diff(target) as usize;
+        let cell = (0..cells.len()).min_by_key(|&cell| cost(cell)).unwrap_or(0);
+
+        let movement = if cell > pointer { ">" } else { "<" };
+        program.push_str(&movement.repeat(pointer.abs_diff(cell)));
+        let adjustment = if target > cells[cell] { "+" } else { "-" };
+        program.push_str(&adjustment.repeat(cells[cell].abs_diff(target) as usize));
+        program.push('.');
+
+        pointer = cell;
+        cells[cell] = target;
+    }
+
+    program
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lex, Interpreter, InterpreterOptions};
+    use std::io;
+
+    fn output(program: &str) -> Vec<u8> {
+        let tokens = lex(program);
+        let options = InterpreterOptions::new().writer(io::sink());
+        let mut interpreter = Interpreter::new(&tokens, options).unwrap();
+        interpreter.interpret().unwrap()
+    }
+
+    #[test]
+    fn test_prints_text() {
+        let texts: [&[u8]; 5] = [
+            b"",
+            b"A",
+            b"Hello World!\n",
+            "Grüße aus Brainfuck ✓".as_bytes(),
+            b"the quick brown fox jumps over the lazy dog. THE QUICK BROWN FOX!",
+        ];
+
+        for text in texts {
+            let program = text_to_brainfuck(text);
+            assert_eq!(output(&program), text, "{}", program);
+        }
+    }
+
+    #[test]
+    fn test_all_bytes() {
+        let text: Vec<u8> = (0..=255).rev().chain(0..=255).collect();
+        assert_eq!(output(&text_to_brainfuck(&text)), text);
+    }
+
+    #[test]
+    fn test_compact() {
+        let program = text_to_brainfuck(b"Hello World!\n");
+        assert!(program.contains('['));
+        assert!(program.len() < 120, "{}", program);
+
+        // repeated bytes are printed from the same cell
+        assert!(text_to_brainfuck(b"aaaa").ends_with("...."));
+    }
+}
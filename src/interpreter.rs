@@ -1,39 +1,220 @@
 use custom_error::custom_error;
-use std::cmp::Ordering;
-use std::error::Error;
-use std::io::Read;
-
-use crate::token::TokenValue;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+
+use crate::check::check_brackets;
+use crate::options::InterpreterOptions;
+use crate::output::Output;
+use crate::token::{Span, TokenValue};
+use crate::CancellationToken;
+use crate::Error;
 use crate::State;
 use crate::Token;
 
 custom_error! { pub InterpreterError
-    TokenIndexOutOfBound = "token index is out of bound",
-    NestingError = "loop nesting is incorrect"
+    StepLimitExceeded{steps: u64, pointer: usize, cell: u32} =
+        "step limit exceeded after {steps} instructions (pointer: {pointer}, cell value: {cell})",
+    Timeout{steps: u64, pointer: usize, cell: u32} =
+        "timeout exceeded after {steps} instructions (pointer: {pointer}, cell value: {cell})",
+    Cancelled{steps: u64, pointer: usize, cell: u32} =
+        "cancelled after {steps} instructions (pointer: {pointer}, cell value: {cell})",
+    EndOfInput = "end of input reached while reading with ','",
+    InfiniteLoop{steps: u64, pointer: usize, cell: u32} =
+        "infinite loop detected after {steps} instructions, an iteration repeated the state of the previous one (pointer: {pointer}, cell value: {cell})"
 }
 
-pub struct Interpreter<'a> {
-    pub state: State,
-    pub tokens: &'a Vec<Token>,
+/// An error raised while executing a program together with the instruction that failed and the
+/// state at the time of the failure
+#[derive(Debug)]
+pub struct RuntimeError {
+    /// The underlying state transition, interpreter, or I/O error
+    pub error: Box<Error>,
+    /// Index of the failed instruction in the token stream
     pub token_index: usize,
+    /// Location of the failed instruction in the program source
+    pub span: Span,
+    /// Number of executed instructions including the failed one
+    pub steps: u64,
+    pub pointer: usize,
+    pub cell: u32,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // tokens that were not lexed from a source have no line
+        match self.span.line {
+            0 => write!(f, "{} at token {}", self.error, self.token_index),
+            line => write!(
+                f,
+                "{} at line {}, column {}",
+                self.error, line, self.span.column
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+/// Behaviour of `,` when the input is exhausted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EofPolicy {
+    /// The current cell keeps its value
+    #[default]
+    Unchanged,
+    /// The current cell is set to 0
+    Zero,
+    /// The current cell is set to the maximum cell value (-1)
+    MaxValue,
+    /// Reading beyond the end of the input is an EndOfInput error
+    Error,
+}
+
+/// Number of executed instructions between two checks of the timeout and the cancellation token
+const CHECK_INTERVAL: u64 = 1024;
+
+/// The current iteration of a running loop, used to detect infinite loops
+struct LoopIteration {
+    /// Index of the '[' of the loop
+    opening: usize,
+    /// Pointer at the start of the iteration
+    pointer: usize,
+    /// Values at the start of the iteration of the cells read or written during the iteration
+    cells: HashMap<usize, u32>,
+    /// Whether input was read during the iteration
+    input: bool,
+}
+
+pub struct Interpreter<'a> {
+    pub(crate) state: State,
+    tokens: &'a [Token],
+    token_index: usize,
+    steps: u64,
+    max_steps: Option<u64>,
+    timeout: Option<Duration>,
+    cancellation_token: Option<CancellationToken>,
+    writer: Output,
+    reader: Box<dyn Read + Send>,
+    eof_policy: EofPolicy,
+    /// Receives one line per executed instruction if set
+    tracer: Option<Box<dyn Write + Send>>,
+    /// Counts how often each token was executed if set
+    pub(crate) profile: Option<Vec<u64>>,
+    /// Abort loops whose iterations repeat the same state
+    detect_infinite_loops: bool,
+    /// Iterations of the running loops, innermost last, if infinite loops are detected
+    loop_iterations: Vec<LoopIteration>,
 }
 
 impl<'a> Interpreter<'a> {
-    pub fn new(tokens: &'a Vec<Token>) -> Interpreter<'a> {
-        let state = State::new();
+    /// Creates an interpreter that runs the token stream with the given options
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens` - Token stream ending with the end token, unmatched brackets are a Syntax
+    ///   error
+    /// * `options` - Semantics, limits, and I/O, an invalid combination is an Options error
+    pub fn new(tokens: &'a [Token], options: InterpreterOptions) -> Result<Interpreter<'a>, Error> {
+        check_brackets(tokens)?;
+        options.validate()?;
+
+        let state = options.build_state();
+        Ok(Interpreter::with_state(tokens, state, options))
+    }
+
+    /// Creates an interpreter that runs the token stream on a given state with the limits and
+    /// I/O of the options, the tape settings of the options are ignored and the brackets of the
+    /// token stream must have been checked
+    pub(crate) fn with_state(
+        tokens: &'a [Token],
+        state: State,
+        mut options: InterpreterOptions,
+    ) -> Interpreter<'a> {
+        let (reader, writer) = options.take_io();
         Interpreter {
             state,
             tokens,
             token_index: 0,
+            steps: 0,
+            max_steps: options.max_steps,
+            timeout: options.timeout,
+            cancellation_token: options.cancellation_token,
+            writer,
+            reader,
+            eof_policy: options.eof_policy,
+            tracer: options.tracer,
+            profile: options.profile.then(|| vec![0; tokens.len()]),
+            detect_infinite_loops: options.detect_infinite_loops,
+            loop_iterations: Vec::new(),
         }
     }
 
+    /// Returns the tape and the pointer
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Returns the token stream that is run
+    pub fn tokens(&self) -> &[Token] {
+        self.tokens
+    }
+
+    /// Returns the index of the next instruction in the token stream
+    pub fn token_index(&self) -> usize {
+        self.token_index
+    }
+
+    /// Returns the number of executed instructions
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
     /// Interprets the token stream
-    pub fn interpret(&mut self) -> Result<String, Box<dyn Error>> {
+    ///
+    /// Errors raised by an instruction are wrapped in a RuntimeError.
+    pub fn interpret(&mut self) -> Result<Vec<u8>, Error> {
+        let mut output = Vec::new();
+        let result = self
+            .execute(&mut output)
+            .map_err(|error| self.runtime_error(error));
+
+        // pending output is flushed even if the program was aborted
+        self.writer.flush()?;
+        result.map(|_| output)
+    }
+
+    /// Executes tokens until the end token is reached and collects the output
+    fn execute(&mut self, output: &mut Vec<u8>) -> Result<(), Error> {
         let mut token = &self.tokens[self.token_index];
-        let mut output = String::new();
+        let start = Instant::now();
 
         while token.value != TokenValue::End {
+            self.check_limits(start)?;
+            self.steps += 1;
+
+            if let Some(tracer) = &mut self.tracer {
+                writeln!(
+                    tracer,
+                    "step {:>8}  token {:>6}  {}  pointer {:>6}  cell {}",
+                    self.steps,
+                    self.token_index,
+                    token.value.to_char().unwrap_or(' '),
+                    self.state.pointer,
+                    self.state.get_cell_value()
+                )?;
+            }
+            if let Some(profile) = &mut self.profile {
+                profile[self.token_index] += 1;
+            }
+            if self.detect_infinite_loops {
+                self.track_loops(&token.value)?;
+            }
+
             match token.value {
                 TokenValue::MoveRight => {
                     self.state.move_right()?;
@@ -52,35 +233,43 @@ impl<'a> Interpreter<'a> {
                     self.token_index += 1;
                 }
                 TokenValue::Output => {
-                    let c = self.state.get_cell_value() as char;
-                    output.push(c);
-                    print!("{}", c);
+                    // cell values are emitted as raw bytes so that UTF-8 and binary output stays intact,
+                    // cells wider than 8 bits are truncated to their lowest byte
+                    let byte = self.state.get_cell_value() as u8;
+                    output.push(byte);
+                    self.writer.write_byte(byte)?;
                     self.token_index += 1;
                 }
                 TokenValue::Input => {
-                    // TODO pass a different input stream to be able to read from a file or a pipe
-                    let input = std::io::stdin()
-                        .bytes()
-                        .next()
-                        .and_then(|result| result.ok())
-                        .unwrap();
-                    self.state.set_cell_value(input);
+                    self.writer.before_input()?;
+
+                    match self.read_byte()? {
+                        Some(byte) => self.state.set_cell_value(byte as u32),
+                        None => match self.eof_policy {
+                            EofPolicy::Unchanged => {}
+                            EofPolicy::Zero => self.state.set_cell_value(0),
+                            EofPolicy::MaxValue => self
+                                .state
+                                .set_cell_value(self.state.cell_width().max_value()),
+                            EofPolicy::Error => return Err(InterpreterError::EndOfInput.into()),
+                        },
+                    }
                     self.token_index += 1;
                 }
-                TokenValue::JumpForwardIfZero => match self.state.cells[self.state.pointer] {
+                TokenValue::JumpForwardIfZero => match self.state.get_cell_value() {
                     0 => {
-                        self.jump_forward()?;
+                        self.jump_forward();
                     }
                     _ => {
                         self.token_index += 1;
                     }
                 },
-                TokenValue::JumpBackwardIfNonZero => match self.state.cells[self.state.pointer] {
+                TokenValue::JumpBackwardIfNonZero => match self.state.get_cell_value() {
                     0 => {
                         self.token_index += 1;
                     }
                     _ => {
-                        self.jump_backward()?;
+                        self.jump_backward();
                     }
                 },
                 _ => {}
@@ -88,11 +277,149 @@ impl<'a> Interpreter<'a> {
             token = &self.tokens[self.token_index];
         }
 
-        Ok(output)
+        Ok(())
+    }
+
+    /// Records the cells accessed by an instruction for every running loop and checks whether the
+    /// iteration of a loop that ends with this instruction repeated the state of its start
+    ///
+    /// If the pointer and every cell read or written during an iteration are the same at its end
+    /// as at its start, the next iteration does exactly the same and the loop never ends.
+    fn track_loops(&mut self, value: &TokenValue) -> Result<(), InterpreterError> {
+        let pointer = self.state.pointer;
+        let cell = self.state.cell(pointer);
+
+        if matches!(value, TokenValue::MoveLeft | TokenValue::MoveRight) {
+            return Ok(());
+        }
+        // the first access of a cell in an iteration sees its value at the start of the iteration
+        for iteration in &mut self.loop_iterations {
+            iteration.cells.entry(pointer).or_insert(cell);
+            iteration.input |= *value == TokenValue::Input;
+        }
+
+        match value {
+            TokenValue::JumpForwardIfZero if cell != 0 => {
+                self.loop_iterations.push(LoopIteration {
+                    opening: self.token_index,
+                    pointer,
+                    cells: HashMap::from([(pointer, cell)]),
+                    input: false,
+                });
+            }
+            TokenValue::JumpBackwardIfNonZero if cell == 0 => {
+                self.loop_iterations.pop();
+            }
+            TokenValue::JumpBackwardIfNonZero => {
+                let Some(iteration) = self.loop_iterations.last_mut() else {
+                    return Ok(());
+                };
+                let repeated = !iteration.input
+                    && iteration.pointer == pointer
+                    && iteration
+                        .cells
+                        .iter()
+                        .all(|(&index, &value)| self.state.cell(index) == value);
+
+                if repeated {
+                    // the error points at the start of the loop
+                    self.token_index = iteration.opening;
+                    return Err(InterpreterError::InfiniteLoop {
+                        steps: self.steps,
+                        pointer,
+                        cell,
+                    });
+                }
+                iteration.pointer = pointer;
+                iteration.cells.clear();
+                iteration.input = false;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Wraps an error with the current instruction and state
+    fn runtime_error(&self, error: Error) -> Error {
+        let span = self
+            .tokens
+            .get(self.token_index)
+            .map_or(Span::default(), |token| token.span);
+
+        Error::from(RuntimeError {
+            error: Box::new(error),
+            token_index: self.token_index,
+            span,
+            steps: self.steps,
+            pointer: self.state.pointer,
+            cell: if self.state.pointer < self.state.tape_length() {
+                self.state.get_cell_value()
+            } else {
+                0
+            },
+        })
+    }
+
+    /// Reads a single byte from the reader, returns None at the end of the input
+    fn read_byte(&mut self) -> std::io::Result<Option<u8>> {
+        let mut buffer = [0];
+        loop {
+            match self.reader.read(&mut buffer) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(buffer[0])),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Checks the step limit, the timeout, and the cancellation token before the next instruction
+    /// is executed
+    fn check_limits(&self, start: Instant) -> Result<(), InterpreterError> {
+        if let Some(max_steps) = self.max_steps {
+            if self.steps >= max_steps {
+                return Err(InterpreterError::StepLimitExceeded {
+                    steps: self.steps,
+                    pointer: self.state.pointer,
+                    cell: self.state.get_cell_value(),
+                });
+            }
+        }
+
+        // reading the clock or an atomic is comparatively expensive, therefore it is only done
+        // periodically
+        if !self.steps.is_multiple_of(CHECK_INTERVAL) {
+            return Ok(());
+        }
+
+        if let Some(timeout) = self.timeout {
+            if start.elapsed() > timeout {
+                return Err(InterpreterError::Timeout {
+                    steps: self.steps,
+                    pointer: self.state.pointer,
+                    cell: self.state.get_cell_value(),
+                });
+            }
+        }
+
+        if let Some(cancellation_token) = &self.cancellation_token {
+            if cancellation_token.is_cancelled() {
+                return Err(InterpreterError::Cancelled {
+                    steps: self.steps,
+                    pointer: self.state.pointer,
+                    cell: self.state.get_cell_value(),
+                });
+            }
+        }
+
+        Ok(())
     }
 
     /// Jumps forward in the token stream from current token index to matching ]
-    pub fn jump_forward(&mut self) -> Result<(), InterpreterError> {
+    ///
+    /// The brackets were checked when the interpreter was created, so the matching ] exists.
+    fn jump_forward(&mut self) {
         // move forward to find matching ]
         let mut nesting_counter = 0;
         let mut token_search_index = self.token_index + 1;
@@ -104,30 +431,22 @@ impl<'a> Interpreter<'a> {
                 }
                 TokenValue::JumpBackwardIfNonZero => {
                     // no nesting, therefore matching ] was found
-                    match nesting_counter.cmp(&0) {
-                        Ordering::Equal => {
-                            self.token_index = token_search_index + 1;
-                            break;
-                        }
-                        Ordering::Greater => nesting_counter -= 1,
-                        Ordering::Less => return Err(InterpreterError::NestingError),
-                    };
+                    if nesting_counter == 0 {
+                        self.token_index = token_search_index + 1;
+                        break;
+                    }
+                    nesting_counter -= 1;
                 }
                 _ => {}
             }
             token_search_index += 1;
-
-            // check if token_search_index is out of bounds
-            if token_search_index > self.tokens.len() {
-                return Err(InterpreterError::TokenIndexOutOfBound);
-            }
         }
-
-        Ok(())
     }
 
     /// Jumps backward in the token stream from current token index to matching [
-    pub fn jump_backward(&mut self) -> Result<(), InterpreterError> {
+    ///
+    /// The brackets were checked when the interpreter was created, so the matching [ exists.
+    fn jump_backward(&mut self) {
         // move backward to find matching [
         let mut nesting_counter = 0;
         let mut token_search_index = self.token_index - 1;
@@ -139,34 +458,41 @@ impl<'a> Interpreter<'a> {
                 }
                 TokenValue::JumpForwardIfZero => {
                     // no nesting, therefore matching [ was found
-                    match nesting_counter.cmp(&0) {
-                        Ordering::Equal => {
-                            self.token_index = token_search_index + 1;
-                            break;
-                        }
-                        Ordering::Greater => {
-                            nesting_counter -= 1;
-                        }
-                        Ordering::Less => return Err(InterpreterError::NestingError),
+                    if nesting_counter == 0 {
+                        self.token_index = token_search_index + 1;
+                        break;
                     }
+                    nesting_counter -= 1;
                 }
                 _ => {}
             }
             token_search_index -= 1;
-
-            // check if token_search_index is out of bounds
-            if token_search_index > self.tokens.len() {
-                return Err(InterpreterError::TokenIndexOutOfBound);
-            }
         }
-
-        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lex;
+    use crate::state::{OverflowMode, StateTransitionError};
+
+    fn runtime_error(error: &Error) -> &RuntimeError {
+        match error {
+            Error::Runtime { source } => source,
+            _ => panic!(
+                "errors of interpret should be runtime errors, got: {}",
+                error
+            ),
+        }
+    }
+
+    fn interpreter_error(error: &Error) -> Option<&InterpreterError> {
+        match runtime_error(error).error.as_ref() {
+            Error::Interpreter { source } => Some(source),
+            _ => None,
+        }
+    }
 
     #[test]
     fn test_linear_program() {
@@ -183,13 +509,13 @@ mod tests {
             Token::build_end(),
         ];
 
-        let mut interpreter = Interpreter::new(&tokens);
+        let mut interpreter = Interpreter::new(&tokens, InterpreterOptions::new()).unwrap();
         interpreter.interpret().unwrap();
         assert_eq!(interpreter.token_index, tokens.len() - 1);
 
         let state = interpreter.state;
         assert_eq!(state.pointer, 0);
-        assert_eq!(state.cells[0], 3);
+        assert_eq!(state.cell(0), 3);
     }
 
     #[test]
@@ -207,13 +533,13 @@ mod tests {
             Token::build_end(),
         ];
 
-        let mut interpreter = Interpreter::new(&tokens);
+        let mut interpreter = Interpreter::new(&tokens, InterpreterOptions::new()).unwrap();
         interpreter.interpret().unwrap();
         assert_eq!(interpreter.token_index, tokens.len() - 1);
 
         let state = interpreter.state;
         assert_eq!(state.pointer, 2);
-        assert_eq!(state.cells[1], 1);
+        assert_eq!(state.cell(1), 1);
     }
 
     #[test]
@@ -230,14 +556,14 @@ mod tests {
             Token::build_end(),
         ];
 
-        let mut interpreter = Interpreter::new(&tokens);
+        let mut interpreter = Interpreter::new(&tokens, InterpreterOptions::new()).unwrap();
         interpreter.interpret().unwrap();
         assert_eq!(interpreter.token_index, tokens.len() - 1);
 
         let state = interpreter.state;
         assert_eq!(state.pointer, 1);
-        assert_eq!(state.cells[0], 1);
-        assert_eq!(state.cells[1], 0);
+        assert_eq!(state.cell(0), 1);
+        assert_eq!(state.cell(1), 0);
     }
 
     #[test]
@@ -259,14 +585,15 @@ mod tests {
         tokens.push(Token::build('.').unwrap());
         tokens.push(Token::build_end());
 
-        let mut interpreter = Interpreter::new(&tokens);
-        interpreter.interpret().unwrap();
+        let mut interpreter = Interpreter::new(&tokens, InterpreterOptions::new()).unwrap();
+        let output = interpreter.interpret().unwrap();
         assert_eq!(interpreter.token_index, tokens.len() - 1);
+        assert_eq!(output, b"!");
 
         let state = interpreter.state;
         assert_eq!(state.pointer, 1);
-        assert_eq!(state.cells[0], 0);
-        assert_eq!(state.cells[1], 33);
+        assert_eq!(state.cell(0), 0);
+        assert_eq!(state.cell(1), 33);
     }
 
     #[test]
@@ -310,16 +637,16 @@ mod tests {
             Token::build_end(),
         ];
 
-        let mut interpreter = Interpreter::new(&tokens);
+        let mut interpreter = Interpreter::new(&tokens, InterpreterOptions::new()).unwrap();
         interpreter.interpret().unwrap();
         assert_eq!(interpreter.token_index, tokens.len() - 1);
 
         let state = interpreter.state;
         assert_eq!(state.pointer, 0);
-        assert_eq!(state.cells[0], 0);
-        assert_eq!(state.cells[1], 0);
-        assert_eq!(state.cells[2], 0);
-        assert_eq!(state.cells[3], 2);
+        assert_eq!(state.cell(0), 0);
+        assert_eq!(state.cell(1), 0);
+        assert_eq!(state.cell(2), 0);
+        assert_eq!(state.cell(3), 2);
     }
 
     #[test]
@@ -362,8 +689,7 @@ mod tests {
             Token::build_end(),
         ];
 
-        let mut interpreter = Interpreter::new(&tokens);
-        assert!(interpreter.interpret().is_err());
+        assert!(Interpreter::new(&tokens, InterpreterOptions::new()).is_err());
     }
 
     #[test]
@@ -406,7 +732,284 @@ mod tests {
             Token::build_end(),
         ];
 
-        let mut interpreter = Interpreter::new(&tokens);
-        assert!(interpreter.interpret().is_err());
+        assert!(Interpreter::new(&tokens, InterpreterOptions::new()).is_err());
+    }
+
+    #[test]
+    fn test_step_limit() {
+        // program: +[]
+        let tokens = vec![
+            Token::build('+').unwrap(),
+            Token::build('[').unwrap(),
+            Token::build(']').unwrap(),
+            Token::build_end(),
+        ];
+
+        let options = InterpreterOptions::new().max_steps(100);
+        let mut interpreter = Interpreter::new(&tokens, options).unwrap();
+
+        match interpreter.interpret() {
+            Err(e) => match interpreter_error(&e) {
+                Some(InterpreterError::StepLimitExceeded {
+                    steps,
+                    pointer,
+                    cell,
+                }) => {
+                    assert_eq!(*steps, 100);
+                    assert_eq!(*pointer, 0);
+                    assert_eq!(*cell, 1);
+                }
+                _ => panic!("expected a StepLimitExceeded error, got: {}", e),
+            },
+            Ok(_) => panic!("an infinite loop should exceed the step limit"),
+        }
+        assert_eq!(interpreter.steps, 100);
+        assert_eq!(interpreter.state.cell(0), 1);
+    }
+
+    #[test]
+    fn test_step_limit_not_reached() {
+        // program: ++[-]
+        let tokens = vec![
+            Token::build('+').unwrap(),
+            Token::build('+').unwrap(),
+            Token::build('[').unwrap(),
+            Token::build('-').unwrap(),
+            Token::build(']').unwrap(),
+            Token::build_end(),
+        ];
+
+        let options = InterpreterOptions::new().max_steps(7);
+        let mut interpreter = Interpreter::new(&tokens, options).unwrap();
+        interpreter.interpret().unwrap();
+        assert_eq!(interpreter.steps, 7);
+    }
+
+    #[test]
+    fn test_timeout() {
+        // program: +[]
+        let tokens = vec![
+            Token::build('+').unwrap(),
+            Token::build('[').unwrap(),
+            Token::build(']').unwrap(),
+            Token::build_end(),
+        ];
+
+        let options = InterpreterOptions::new().timeout(Duration::from_millis(10));
+        let mut interpreter = Interpreter::new(&tokens, options).unwrap();
+
+        match interpreter.interpret() {
+            Err(e) => assert!(
+                matches!(
+                    interpreter_error(&e),
+                    Some(InterpreterError::Timeout { .. })
+                ),
+                "expected a Timeout error, got: {}",
+                e
+            ),
+            Ok(_) => panic!("an infinite loop should exceed the timeout"),
+        }
+    }
+
+    #[test]
+    fn test_cancellation() {
+        // program: +[>+<]
+        let tokens = vec![
+            Token::build('+').unwrap(),
+            Token::build('[').unwrap(),
+            Token::build('>').unwrap(),
+            Token::build('<').unwrap(),
+            Token::build(']').unwrap(),
+            Token::build_end(),
+        ];
+
+        let cancellation_token = CancellationToken::new();
+        let handle = {
+            let cancellation_token = cancellation_token.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(10));
+                cancellation_token.cancel();
+            })
+        };
+
+        let options = InterpreterOptions::new().cancellation_token(cancellation_token);
+        let mut interpreter = Interpreter::new(&tokens, options).unwrap();
+
+        match interpreter.interpret() {
+            Err(e) => assert!(
+                matches!(
+                    interpreter_error(&e),
+                    Some(InterpreterError::Cancelled { .. })
+                ),
+                "expected a Cancelled error, got: {}",
+                e
+            ),
+            Ok(_) => panic!("a cancelled interpretation should not finish"),
+        }
+        handle.join().unwrap();
+
+        // the state stays inspectable after the cancellation
+        assert_eq!(interpreter.state.cell(0), 1);
+        assert!(interpreter.steps > 0);
+    }
+
+    #[test]
+    fn test_interpret_on_another_thread() {
+        // program: +[]
+        let tokens = vec![
+            Token::build('+').unwrap(),
+            Token::build('[').unwrap(),
+            Token::build(']').unwrap(),
+            Token::build_end(),
+        ];
+
+        let cancellation_token = CancellationToken::new();
+        let options = InterpreterOptions::new()
+            .cancellation_token(cancellation_token.clone())
+            .reader(std::io::empty())
+            .writer(std::io::sink());
+        let mut interpreter = Interpreter::new(&tokens, options).unwrap();
+
+        // the interpreter can be moved to a worker thread and cancelled from the embedding one
+        let result = std::thread::scope(|scope| {
+            let handle = scope.spawn(move || interpreter.interpret());
+            cancellation_token.cancel();
+            handle.join().unwrap()
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_output_is_byte_exact() {
+        // program: +_200_+.
+        let mut tokens = Vec::new();
+        for _ in 0..200 {
+            tokens.push(Token::build('+').unwrap());
+        }
+        tokens.push(Token::build('.').unwrap());
+        tokens.push(Token::build_end());
+
+        let mut interpreter = Interpreter::new(&tokens, InterpreterOptions::new()).unwrap();
+        let output = interpreter.interpret().unwrap();
+        assert_eq!(output, vec![200]);
+    }
+
+    #[test]
+    fn test_input_and_eof_policy() {
+        // program: ,>,>+,>+,
+        let tokens = vec![
+            Token::build(',').unwrap(),
+            Token::build('>').unwrap(),
+            Token::build(',').unwrap(),
+            Token::build('>').unwrap(),
+            Token::build('+').unwrap(),
+            Token::build(',').unwrap(),
+            Token::build('>').unwrap(),
+            Token::build('+').unwrap(),
+            Token::build(',').unwrap(),
+            Token::build_end(),
+        ];
+
+        let cases = [
+            (EofPolicy::Unchanged, [b'a' as u32, 0, 1, 1]),
+            (EofPolicy::Zero, [b'a' as u32, 0, 0, 0]),
+            (EofPolicy::MaxValue, [b'a' as u32, 255, 255, 255]),
+        ];
+        for (eof_policy, cells) in cases {
+            let options = InterpreterOptions::new()
+                .reader(&b"a"[..])
+                .eof_policy(eof_policy);
+            let mut interpreter = Interpreter::new(&tokens, options).unwrap();
+            interpreter.interpret().unwrap();
+            let tape: Vec<u32> = (0..4).map(|index| interpreter.state.cell(index)).collect();
+            assert_eq!(tape, cells, "{:?}", eof_policy);
+        }
+
+        let options = InterpreterOptions::new()
+            .reader(&b"a"[..])
+            .eof_policy(EofPolicy::Error);
+        let mut interpreter = Interpreter::new(&tokens, options).unwrap();
+        match interpreter.interpret() {
+            Err(e) => assert!(matches!(
+                interpreter_error(&e),
+                Some(InterpreterError::EndOfInput)
+            )),
+            Ok(_) => panic!("reading beyond the input should fail with EofPolicy::Error"),
+        }
+    }
+
+    #[test]
+    fn test_profile() {
+        // program: ++[-]
+        let tokens = vec![
+            Token::build('+').unwrap(),
+            Token::build('+').unwrap(),
+            Token::build('[').unwrap(),
+            Token::build('-').unwrap(),
+            Token::build(']').unwrap(),
+            Token::build_end(),
+        ];
+
+        let options = InterpreterOptions::new().profile(true);
+        let mut interpreter = Interpreter::new(&tokens, options).unwrap();
+        interpreter.interpret().unwrap();
+        assert_eq!(interpreter.profile.unwrap(), vec![1, 1, 1, 2, 2, 0]);
+    }
+
+    #[test]
+    fn test_detect_infinite_loops() {
+        for source in ["+[]", "+[-+]", "+[>+[-]<]", "+>+[<[-]+>]", "++[>>[-]<<-+]"] {
+            let tokens = lex(source);
+            let options = InterpreterOptions::new().detect_infinite_loops(true);
+            let mut interpreter = Interpreter::new(&tokens, options).unwrap();
+
+            let error = interpreter.interpret().unwrap_err();
+            assert!(
+                matches!(
+                    interpreter_error(&error),
+                    Some(InterpreterError::InfiniteLoop { .. })
+                ),
+                "{}",
+                source
+            );
+            // the error points at the start of the loop
+            assert_eq!(
+                runtime_error(&error).span.column,
+                source.find('[').unwrap() + 1
+            );
+        }
+
+        // loops that make progress, read input, or leave the loop are not aborted
+        for source in ["++[-]", "+[>+<-]", "+[+]", "+[,]", "++++[>+[-]<-]"] {
+            let tokens = lex(source);
+            let options = InterpreterOptions::new()
+                .overflow_mode(OverflowMode::Wrap)
+                .eof_policy(EofPolicy::Zero)
+                .reader(&b"ab"[..])
+                .detect_infinite_loops(true);
+            let mut interpreter = Interpreter::new(&tokens, options).unwrap();
+            assert!(interpreter.interpret().is_ok(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_runtime_error_context() {
+        let tokens = lex("+\n>>\n<<<");
+
+        let mut interpreter = Interpreter::new(&tokens, InterpreterOptions::new()).unwrap();
+        let error = interpreter.interpret().unwrap_err();
+        let error = runtime_error(&error);
+
+        assert!(matches!(
+            error.error.as_ref(),
+            Error::StateTransition {
+                source: StateTransitionError::PointerUnderflow
+            }
+        ));
+        assert_eq!(error.token_index, 5);
+        assert_eq!((error.span.line, error.span.column), (3, 3));
+        assert_eq!(error.steps, 6);
+        assert_eq!((error.pointer, error.cell), (0, 1));
+        assert!(error.to_string().ends_with(" at line 3, column 3"));
     }
 }
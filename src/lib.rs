@@ -1,27 +1,378 @@
-use std::error::Error;
-use std::fs;
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Cursor, Read};
+use std::path::Path;
 
+mod assembler;
+mod bounds;
+mod cancellation;
+mod check;
+mod compiler;
 mod config;
+mod config_file;
+mod diagnostic;
+mod dialect;
+mod error;
+mod exit_code;
+mod formatter;
+mod generator;
 mod interpreter;
+mod linter;
+mod minifier;
+mod options;
+mod output;
+mod preprocessor;
+mod profile;
+mod program;
+mod repl;
 mod state;
 mod token;
 
-pub use config::Config;
-use interpreter::Interpreter;
-use state::State;
-use token::{Token, TokenValue};
+pub use assembler::{compile_macros, Assembly, MacroError};
+pub use bounds::{bounds_warnings, pointer_ranges, PointerRange};
+pub use cancellation::CancellationToken;
+pub use check::SyntaxError;
+pub use config::{Command, Config, ConfigError, USAGE};
+pub use config_file::{find_config_file, CONFIG_FILE_NAME};
+pub use diagnostic::{Diagnostic, ErrorFormat, Label, Severity};
+pub use dialect::Dialect;
+pub use error::{Error, FilesError};
+pub use exit_code::ExitCode;
+pub use generator::text_to_brainfuck;
+pub use interpreter::{EofPolicy, Interpreter, InterpreterError, RuntimeError};
+pub use options::{InterpreterOptions, OptionsError};
+pub use output::FlushPolicy;
+pub use preprocessor::{IncludeError, SourceFile};
+pub use program::Program;
+pub use repl::repl;
+pub use state::{
+    BoundsMode, CellWidth, OverflowMode, State, StateTransitionError, DEFAULT_TAPE_LENGTH,
+};
+pub use token::{Span, Token, TokenValue};
 
 /// Runs a brainfuck program
 ///
 /// # Arguments
 ///
+/// * `config` - Config containing the file path to the brainfuck program, the tape semantics and
+///   execution limits, the input and output files, and an optional cancellation token
+pub fn run(config: Config) -> Result<Vec<u8>, Error> {
+    let program_string = read_program(&config)?;
+    let (output, _) = run_with_final_cell(&program_string, config)?;
+    Ok(output)
+}
+
+/// Runs a brainfuck program given as a string
+///
+/// # Arguments
+///
+/// * `program_string` - A string containing the brainfuck program
+/// * `options` - Semantics, limits, and I/O of the interpreter
+pub fn run_source(program_string: &str, options: InterpreterOptions) -> Result<Vec<u8>, Error> {
+    let program = Program::parse(program_string)?;
+    let mut interpreter = Interpreter::new(program.tokens(), options)?;
+    interpreter.interpret()
+}
+
+/// Runs a brainfuck program and returns its output and the value of the cell under the pointer at
+/// the end of the program
+///
+/// # Arguments
+///
+/// * `program_string` - A string containing the brainfuck program
+/// * `config` - Config as for `run`, the program location in the config is only used to resolve
+///   includes
+pub fn run_with_final_cell(program_string: &str, config: Config) -> Result<(Vec<u8>, u32), Error> {
+    let program = parse(program_string, &config)?;
+    let options = interpreter_options(&config);
+    let mut interpreter = build_interpreter(&program, options, config)?;
+    let output = interpreter
+        .interpret()
+        .map_err(|error| error.with_files(program.files()))?;
+    Ok((output, interpreter.state().get_cell_value()))
+}
+
+/// Checks a brainfuck program for unmatched brackets
+///
+/// # Arguments
+///
+/// * `program_string` - A string containing the brainfuck program
+/// * `config` - Config containing the file path to the brainfuck program, includes are resolved
+///   relative to it
+pub fn check(program_string: &str, config: &Config) -> Result<(), Error> {
+    parse(program_string, config)?;
+    Ok(())
+}
+
+/// Compiles a brainfuck program to C source code
+///
+/// # Arguments
+///
+/// * `program_string` - A string containing the brainfuck program
+/// * `config` - Config containing the file path to the brainfuck program and the tape semantics
+pub fn compile(program_string: &str, config: &Config) -> Result<String, Error> {
+    let program = parse(program_string, config)?;
+    Ok(compiler::compile_to_c(
+        program.tokens(),
+        config.tape_size,
+        config.cell_width,
+        config.eof_policy,
+    ))
+}
+
+/// Formats a brainfuck program, input embedded after '!' is kept unchanged
+///
+/// # Arguments
+///
+/// * `program_string` - A string containing the brainfuck program
+/// * `config` - Config containing the line width
+pub fn fmt(program_string: &str, config: &Config) -> Result<String, Error> {
+    let (code, input) = split_embedded_input(program_string, config.embedded_input);
+
+    // include directives are kept as they are
+    let program = Program::parse(&without_directives(code, config)?)?;
+    let mut formatted = formatter::format(code, program.tokens(), config.format_width);
+    if let Some(input) = input {
+        formatted.push('!');
+        formatted.push_str(input);
+    }
+    Ok(formatted)
+}
+
+/// Minifies a brainfuck program, included files are inlined with --includes and input embedded
+/// after '!' is kept unchanged
+///
+/// # Arguments
+///
+/// * `program_string` - A string containing the brainfuck program
 /// * `config` - Config containing the file path to the brainfuck program
-pub fn run(config: Config) -> Result<String, Box<dyn Error>> {
-    // read brainfuck file into string
-    let program_string = fs::read_to_string(config.brainfuck_file_path)?;
-    let tokens = lex(&program_string);
+pub fn minify(program_string: &str, config: &Config) -> Result<String, Error> {
+    let (code, input) = split_embedded_input(program_string, config.embedded_input);
+
+    let program = parse_code(code, config)?;
+    let mut minified = minifier::minify(program.tokens());
+    if let Some(input) = input {
+        minified.push('!');
+        minified.push_str(input);
+    }
+    Ok(minified)
+}
+
+/// Lints a brainfuck program and returns its warnings, input embedded after '!' and include
+/// directives are ignored
+///
+/// # Arguments
+///
+/// * `program_string` - A string containing the brainfuck program
+/// * `config` - Config telling whether the program has embedded input and the cell width
+pub fn lint(program_string: &str, config: &Config) -> Result<Vec<Diagnostic>, Error> {
+    let (code, _) = split_embedded_input(program_string, config.embedded_input);
+
+    let program = Program::parse(&without_directives(code, config)?)?;
+    Ok(linter::lint(code, program.tokens(), config.cell_width))
+}
+
+/// Checks statically whether a brainfuck program can move the pointer off the tape and returns a
+/// warning for every instruction that could
+///
+/// # Arguments
+///
+/// * `program_string` - A string containing the brainfuck program
+/// * `config` - Config containing the file path to the brainfuck program and the tape size
+pub fn check_bounds(program_string: &str, config: &Config) -> Result<Vec<Diagnostic>, Error> {
+    let program = parse(program_string, config)?;
+    let tape_length = if config.growable_tape {
+        None
+    } else {
+        Some(config.tape_size)
+    };
+
+    let ranges = pointer_ranges(program.tokens(), tape_length);
+    Ok(bounds_warnings(program.tokens(), &ranges, tape_length))
+}
+
+/// Generates a brainfuck program that prints the text given inline, on stdin (path "-"), or in a
+/// file
+///
+/// # Arguments
+///
+/// * `config` - Config containing the inline text or the file path to the text
+pub fn generate(config: &Config) -> Result<String, Error> {
+    // the text is read as bytes, since it doesn't have to be UTF-8
+    let text = match &config.inline_program {
+        Some(text) => text.as_bytes().to_vec(),
+        None if config.brainfuck_file_path == "-" => {
+            let mut text = Vec::new();
+            io::stdin().read_to_end(&mut text)?;
+            text
+        }
+        None => fs::read(&config.brainfuck_file_path)?,
+    };
+    Ok(text_to_brainfuck(&text))
+}
+
+/// Compiles a program in the macro language to brainfuck
+///
+/// # Arguments
+///
+/// * `program_string` - A string containing the program in the macro language
+/// * `config` - Config containing the cell width the numbers of the program must fit into
+pub fn assemble(program_string: &str, config: &Config) -> Result<String, Error> {
+    Ok(compile_macros(program_string, config.cell_width)?.code)
+}
+
+/// Runs a brainfuck program and traces every executed instruction to stderr
+///
+/// # Arguments
+///
+/// * `program_string` - A string containing the brainfuck program
+/// * `config` - Config as for `run`
+pub fn debug(program_string: &str, config: Config) -> Result<Vec<u8>, Error> {
+    let program = parse(program_string, &config)?;
+    let options = interpreter_options(&config).tracer(io::stderr());
+    let mut interpreter = build_interpreter(&program, options, config)?;
+    interpreter
+        .interpret()
+        .map_err(|error| error.with_files(program.files()))
+}
+
+/// Runs a brainfuck program and returns a report of how often each instruction was executed
+///
+/// # Arguments
+///
+/// * `program_string` - A string containing the brainfuck program
+/// * `config` - Config as for `run`
+pub fn profile(program_string: &str, config: Config) -> Result<String, Error> {
+    let program = parse(program_string, &config)?;
+    let options = interpreter_options(&config).profile(true);
+    let mut interpreter = build_interpreter(&program, options, config)?;
+    interpreter
+        .interpret()
+        .map_err(|error| error.with_files(program.files()))?;
+
+    let counts = interpreter.profile.unwrap_or_default();
+    Ok(profile::report(program.tokens(), &counts))
+}
+
+/// Reads the brainfuck program given inline, on stdin (path "-"), or in a file
+///
+/// # Arguments
+///
+/// * `config` - Config containing the inline program or the file path to the brainfuck program
+pub fn read_program(config: &Config) -> Result<String, Error> {
+    let program_string = match &config.inline_program {
+        Some(program_string) => program_string.clone(),
+        None if config.brainfuck_file_path == "-" => io::read_to_string(io::stdin())?,
+        None => fs::read_to_string(&config.brainfuck_file_path)?,
+    };
+    Ok(program_string)
+}
+
+/// Splits a program into its code and the input embedded after the first '!'
+fn split_embedded_input(program_string: &str, embedded_input: bool) -> (&str, Option<&str>) {
+    if embedded_input {
+        program_string
+            .split_once('!')
+            .map_or((program_string, None), |(code, input)| (code, Some(input)))
+    } else {
+        (program_string, None)
+    }
+}
+
+/// Returns the path of the program file, includes are resolved relative to it
+fn program_path(config: &Config) -> &Path {
+    match config.brainfuck_file_path.as_str() {
+        "-" => Path::new(""),
+        path => Path::new(path),
+    }
+}
+
+/// Lexes a brainfuck program, optionally splitting off embedded input or compiling it from the
+/// macro language first, and checks its brackets
+fn parse(program_string: &str, config: &Config) -> Result<Program, Error> {
+    if config.macros {
+        return Program::parse_macros(program_string, config.cell_width);
+    }
+
+    let (code, input) = split_embedded_input(program_string, config.embedded_input);
+    let program = parse_code(code, config)?;
+    Ok(program.with_input(input))
+}
+
+/// Lexes brainfuck code, with its includes if they are enabled, and checks its brackets
+fn parse_code(code: &str, config: &Config) -> Result<Program, Error> {
+    if config.includes {
+        Program::parse_with_includes(code, program_path(config))
+    } else {
+        Program::parse(code)
+    }
+}
+
+/// Returns the code with its include directives blanked if includes are enabled, otherwise the
+/// directives are comments like any other text
+fn without_directives<'a>(code: &'a str, config: &Config) -> Result<Cow<'a, str>, Error> {
+    if config.includes {
+        Ok(Cow::Owned(preprocessor::blank_directives(code)?))
+    } else {
+        Ok(Cow::Borrowed(code))
+    }
+}
+
+/// Creates an interpreter with the given options and the I/O described by the config
+///
+/// Input embedded in the program takes the place of stdin and the input file.
+fn build_interpreter(
+    program: &Program,
+    mut options: InterpreterOptions,
+    config: Config,
+) -> Result<Interpreter<'_>, Error> {
+    if let Some(cancellation_token) = config.cancellation_token {
+        options = options.cancellation_token(cancellation_token);
+    }
+    if let Some(path) = &config.output_file_path {
+        options = options.writer(File::create(path)?);
+    }
+    if let Some(input) = program.embedded_input() {
+        options = options.reader(Cursor::new(input.to_vec()));
+    } else if let Some(path) = &config.input_file_path {
+        options = options.reader(BufReader::new(File::open(path)?));
+    }
+
+    Interpreter::new(program.tokens(), options)
+}
+
+/// Converts the tape semantics and limits of the config to interpreter options
+fn interpreter_options(config: &Config) -> InterpreterOptions {
+    let mut options = InterpreterOptions::new()
+        .tape_length(config.tape_size)
+        .cell_width(config.cell_width)
+        .overflow_mode(config.overflow_mode)
+        .eof_policy(config.eof_policy)
+        .flush_policy(config.flush_policy)
+        .detect_infinite_loops(config.detect_infinite_loops);
+    if let Some(max_steps) = config.max_steps {
+        options = options.max_steps(max_steps);
+    }
+    if let Some(timeout) = config.timeout {
+        options = options.timeout(timeout);
+    }
+
+    if config.growable_tape {
+        options = options.bounds_mode(BoundsMode::Grow);
+        if let Some(max_tape_bytes) = config.max_tape_bytes {
+            // the initial tape is shrunk to fit into the memory limit
+            let max_tape_length = (max_tape_bytes / config.cell_width.bytes()).max(1);
+            options = options
+                .tape_length(config.tape_size.min(max_tape_length))
+                .max_tape_bytes(max_tape_bytes);
+        }
+    }
+    options
+}
 
-    interpret(&tokens)
+/// Creates a state with the tape described by the config
+fn build_state(config: &Config) -> State {
+    interpreter_options(config).build_state()
 }
 
 /// Returns a vector of Token from a string
@@ -31,27 +382,71 @@ pub fn run(config: Config) -> Result<String, Box<dyn Error>> {
 /// * `program_string` - A string containing the brainfuck program
 pub fn lex(program_string: &str) -> Vec<Token> {
     let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut column = 1;
 
-    for c in program_string.chars() {
+    for (offset, c) in program_string.char_indices() {
         if let Some(t) = Token::build(c) {
-            tokens.push(t);
+            tokens.push(t.with_span(Span {
+                start: offset,
+                end: offset + c.len_utf8(),
+                line,
+                column,
+                file: 0,
+            }));
         };
+
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
     }
-    tokens.push(Token {
-        value: TokenValue::End,
-    });
+    tokens.push(Token::build_end().with_span(Span {
+        start: program_string.len(),
+        end: program_string.len(),
+        line,
+        column,
+        file: 0,
+    }));
     tokens
 }
 
-pub fn interpret(tokens: &Vec<Token>) -> Result<String, Box<dyn Error>> {
-    let mut interpreter = Interpreter::new(tokens);
+/// Returns a vector of Token from the part of a string before its first `!` and the rest of
+/// the string as input for the program
+///
+/// The input is None if the string does not contain a `!`.
+///
+/// # Arguments
+///
+/// * `program_string` - A string containing the brainfuck program and its input
+pub fn lex_with_input(program_string: &str) -> (Vec<Token>, Option<Vec<u8>>) {
+    match program_string.split_once('!') {
+        Some((program, input)) => (lex(program), Some(input.as_bytes().to_vec())),
+        None => (lex(program_string), None),
+    }
+}
+
+pub fn interpret(tokens: &[Token]) -> Result<Vec<u8>, Error> {
+    let mut interpreter = Interpreter::new(tokens, InterpreterOptions::new())?;
     interpreter.interpret()
 }
 
+/// Decodes the raw output of a brainfuck program as UTF-8 text
+///
+/// Invalid UTF-8 sequences are replaced with U+FFFD.
+///
+/// # Arguments
+///
+/// * `output` - The bytes returned by `run` or `interpret`
+pub fn decode_output(output: &[u8]) -> String {
+    String::from_utf8_lossy(output).into_owned()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use token::{Token, TokenValue};
 
     #[test]
     fn test_lexer() {
@@ -59,37 +454,115 @@ mod tests {
 <>+-adsfl ageaf
 .,[qowejga]";
 
+        let span = |start, line, column| Span {
+            start,
+            end: start + 1,
+            line,
+            column,
+            file: 0,
+        };
+
         assert_eq!(
             vec![
                 Token {
-                    value: TokenValue::MoveLeft
+                    value: TokenValue::MoveLeft,
+                    span: span(0, 1, 1)
                 },
                 Token {
-                    value: TokenValue::MoveRight
+                    value: TokenValue::MoveRight,
+                    span: span(1, 1, 2)
                 },
                 Token {
-                    value: TokenValue::IncrementCell
+                    value: TokenValue::IncrementCell,
+                    span: span(2, 1, 3)
                 },
                 Token {
-                    value: TokenValue::DecrementCell
+                    value: TokenValue::DecrementCell,
+                    span: span(3, 1, 4)
                 },
                 Token {
-                    value: TokenValue::Output
+                    value: TokenValue::Output,
+                    span: span(16, 2, 1)
                 },
                 Token {
-                    value: TokenValue::Input
+                    value: TokenValue::Input,
+                    span: span(17, 2, 2)
                 },
                 Token {
-                    value: TokenValue::JumpForwardIfZero
+                    value: TokenValue::JumpForwardIfZero,
+                    span: span(18, 2, 3)
                 },
                 Token {
-                    value: TokenValue::JumpBackwardIfNonZero
+                    value: TokenValue::JumpBackwardIfNonZero,
+                    span: span(26, 2, 11)
                 },
                 Token {
-                    value: TokenValue::End
+                    value: TokenValue::End,
+                    span: Span {
+                        start: 27,
+                        end: 27,
+                        line: 2,
+                        column: 12,
+                        file: 0,
+                    }
                 }
             ],
             lex(program_string)
         );
     }
+
+    #[test]
+    fn test_decode_output() {
+        assert_eq!(decode_output(b"Hello"), "Hello");
+        assert_eq!(decode_output(&[0xc3, 0xa4]), "\u{e4}");
+        assert_eq!(decode_output(&[0x61, 0xff]), "a\u{fffd}");
+    }
+
+    #[test]
+    fn test_run_source() {
+        let options = InterpreterOptions::new().writer(io::sink());
+        let output = run_source("++++++[>++++++++<-]>+.+.", options).unwrap();
+        assert_eq!(output, b"12");
+    }
+
+    #[test]
+    fn test_run_inline_program() {
+        let config = Config {
+            inline_program: Some(String::from("++++++[>++++++++<-]>.")),
+            ..Default::default()
+        };
+        assert_eq!(run(config).unwrap(), b"0");
+    }
+
+    #[test]
+    fn test_run_source_syntax_error() {
+        let result = run_source("+[", InterpreterOptions::new());
+        match result {
+            Err(e) => assert!(matches!(e, Error::Syntax { .. })),
+            Ok(_) => panic!("an unmatched [ should be a syntax error"),
+        }
+    }
+
+    #[test]
+    fn test_lexer_with_input() {
+        let (tokens, input) = lex_with_input(",[.,]!ab!c");
+        assert_eq!(tokens, lex(",[.,]"));
+        assert_eq!(input.unwrap(), b"ab!c");
+
+        let (tokens, input) = lex_with_input(",[.,]");
+        assert_eq!(tokens, lex(",[.,]"));
+        assert!(input.is_none());
+    }
+
+    #[test]
+    fn test_run_embedded_input() {
+        let config = Config {
+            inline_program: Some(String::from(",[.,]!hi[")),
+            embedded_input: true,
+            eof_policy: EofPolicy::Zero,
+            ..Default::default()
+        };
+        // the [ in the input must not be lexed as a command
+        assert_eq!(run(config).unwrap(), b"hi[");
+    }
 }
@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::state::CellWidth;
+use crate::token::{Span, Token, TokenValue};
+
+/// Characters that are easily mistaken for brainfuck commands
+const LOOKALIKES: [(char, char); 14] = [
+    ('–', '-'),
+    ('—', '-'),
+    ('−', '-'),
+    ('＋', '+'),
+    ('‚', ','),
+    ('，', ','),
+    ('·', '.'),
+    ('．', '.'),
+    ('‹', '<'),
+    ('＜', '<'),
+    ('›', '>'),
+    ('＞', '>'),
+    ('［', '['),
+    ('］', ']'),
+];
+
+/// Lints a brainfuck program and returns warnings sorted by their position
+///
+/// The brackets of the program must match.
+///
+/// # Arguments
+///
+/// * `source` - The source of the program
+/// * `tokens` - The tokens lexed from source
+/// * `cell_width` - Number of bits of a cell, larger cell values are not tracked
+pub fn lint(source: &str, tokens: &[Token], cell_width: CellWidth) -> Vec<Diagnostic> {
+    let commands: Vec<&Token> = tokens
+        .iter()
+        .filter(|token| token.value != TokenValue::End)
+        .collect();
+
+    let mut warnings = Vec::new();
+    cancelling_sequences(&commands, &mut warnings);
+    dead_loops(&commands, &mut warnings);
+    Flow::new(cell_width).analyze(&commands, &mut warnings);
+    prose_punctuation(source, &commands, &mut warnings);
+    lookalikes(source, &mut warnings);
+
+    warnings.sort_by_key(|warning| warning.primary.as_ref().map(|label| label.span.start));
+    warnings
+}
+
+/// Warns about adjacent commands that undo each other, like `+-` or `<>`
+fn cancelling_sequences(commands: &[&Token], warnings: &mut Vec<Diagnostic>) {
+    let mut index = 0;
+    while index + 1 < commands.len() {
+        let (first, second) = (commands[index], commands[index + 1]);
+        if !is_inverse(&first.value, &second.value) {
+            index += 1;
+            continue;
+        }
+
+        let sequence = format!(
+            "{}{}",
+            first.value.to_char().unwrap_or(' '),
+            second.value.to_char().unwrap_or(' ')
+        );
+        warnings.push(
+            labelled(
+                Diagnostic::new(
+                    "W0001",
+                    Severity::Warning,
+                    &format!("cancelling sequence '{}'", sequence),
+                ),
+                first.span,
+                second.span,
+                "these commands undo each other",
+                "undone here",
+            )
+            .with_note("remove both commands, they have no effect"),
+        );
+        index += 2;
+    }
+}
+
+/// Warns about loops that start directly after another loop or at the start of the program,
+/// where the cell is always zero
+fn dead_loops(commands: &[&Token], warnings: &mut Vec<Diagnostic>) {
+    for (index, token) in commands.iter().enumerate() {
+        if token.value != TokenValue::JumpForwardIfZero {
+            continue;
+        }
+
+        let warning = Diagnostic::new("W0002", Severity::Warning, "this loop is never entered");
+        match index.checked_sub(1).map(|previous| commands[previous]) {
+            None => warnings.push(
+                warning
+                    .with_primary(token.span, "every cell is zero at the start of the program")
+                    .with_note("loops at the start of a program are sometimes used as comments"),
+            ),
+            Some(previous) if previous.value == TokenValue::JumpBackwardIfNonZero => warnings.push(
+                warning
+                    .with_primary(token.span, "the cell is always zero here")
+                    .with_secondary(previous.span, "the previous loop ends on a zero cell"),
+            ),
+            Some(_) => {}
+        }
+    }
+}
+
+/// Follows the commands that are executed for sure to find pointer movements below cell 0 and
+/// loops that never end
+///
+/// Loops are followed through their first iteration. Code is only known to be executed if every
+/// loop around it is entered on a cell with a known value that isn't zero.
+struct Flow {
+    /// Offset of the pointer from cell 0, unknown after a loop that moves the pointer
+    pointer: Option<isize>,
+    /// Known values of cells, None for cells whose value is unknown
+    cells: HashMap<isize, Option<i64>>,
+    /// Whether cells that are not in `cells` are still zero
+    untouched_zero: bool,
+    /// Whether the current command is executed for sure
+    certain: bool,
+    /// Largest value a cell can hold
+    max_value: i64,
+}
+
+/// State when a loop was entered
+struct Frame {
+    pointer: Option<isize>,
+    certain: bool,
+}
+
+impl Flow {
+    fn new(cell_width: CellWidth) -> Flow {
+        Flow {
+            pointer: Some(0),
+            cells: HashMap::new(),
+            untouched_zero: true,
+            certain: true,
+            max_value: cell_width.max_value() as i64,
+        }
+    }
+
+    fn cell(&self) -> Option<i64> {
+        let pointer = self.pointer?;
+        match self.cells.get(&pointer) {
+            Some(value) => *value,
+            None => self.untouched_zero.then_some(0),
+        }
+    }
+
+    fn set_cell(&mut self, value: Option<i64>) {
+        match self.pointer {
+            // values that could have wrapped around are not tracked
+            Some(pointer) => {
+                let value = value.filter(|value| value.abs() <= self.max_value);
+                self.cells.insert(pointer, value);
+            }
+            None => {
+                self.cells.clear();
+                self.untouched_zero = false;
+            }
+        }
+    }
+
+    /// Stops at the first warning, since the program fails or hangs there
+    fn analyze(mut self, commands: &[&Token], warnings: &mut Vec<Diagnostic>) {
+        let mut frames: Vec<Frame> = Vec::new();
+        let mut index = 0;
+
+        while index < commands.len() {
+            let token = commands[index];
+            match token.value {
+                TokenValue::MoveRight => self.pointer = self.pointer.map(|pointer| pointer + 1),
+                TokenValue::MoveLeft => {
+                    if self.certain && self.pointer == Some(0) {
+                        warnings.push(
+                            Diagnostic::new(
+                                "W0003",
+                                Severity::Warning,
+                                "the pointer moves below cell 0",
+                            )
+                            .with_primary(token.span, "the pointer is at cell 0 here")
+                            .with_note("the program fails when this command is executed"),
+                        );
+                        return;
+                    }
+                    self.pointer = self.pointer.map(|pointer| pointer - 1);
+                }
+                TokenValue::IncrementCell => self.set_cell(self.cell().map(|value| value + 1)),
+                TokenValue::DecrementCell => self.set_cell(self.cell().map(|value| value - 1)),
+                TokenValue::Input => self.set_cell(None),
+                TokenValue::JumpForwardIfZero => {
+                    let after_loop =
+                        index > 0 && commands[index - 1].value == TokenValue::JumpBackwardIfNonZero;
+                    if after_loop || self.cell() == Some(0) {
+                        // the body is never executed
+                        index = matching_bracket(commands, index) + 1;
+                        continue;
+                    }
+
+                    let next = commands[index + 1];
+                    if self.certain && self.cell().is_some() && is_closing(next) {
+                        let mut warning = labelled(
+                            Diagnostic::new("W0004", Severity::Warning, "this loop never ends"),
+                            token.span,
+                            next.span,
+                            "the cell is not zero and the loop doesn't change it",
+                            "the loop ends here",
+                        );
+                        if let Some(unreachable) = commands.get(index + 2) {
+                            warning = warning
+                                .with_secondary(unreachable.span, "this code is unreachable");
+                        }
+                        warnings.push(warning);
+                        return;
+                    }
+
+                    frames.push(Frame {
+                        pointer: self.pointer,
+                        certain: self.certain,
+                    });
+                    self.certain = self.certain && self.cell().is_some();
+                }
+                TokenValue::JumpBackwardIfNonZero => {
+                    if let Some(frame) = frames.pop() {
+                        if self.pointer != frame.pointer {
+                            self.pointer = None;
+                        }
+                        self.certain = frame.certain;
+                    }
+
+                    // the loop could have changed any cell, but the current one is zero
+                    self.cells.clear();
+                    self.untouched_zero = false;
+                    self.set_cell(Some(0));
+                }
+                TokenValue::Output | TokenValue::End => {}
+            }
+            index += 1;
+        }
+    }
+}
+
+/// Warns about '.', ',', and '-' that are part of words or sentences in comments
+fn prose_punctuation(source: &str, commands: &[&Token], warnings: &mut Vec<Diagnostic>) {
+    for token in commands {
+        let before = source[..token.span.start].chars().next_back();
+        let after = source[token.span.end..].chars().next();
+        let is_letter = |c: Option<char>| c.is_some_and(|c| c.is_alphabetic());
+        // numbers like "3.5" or "1,2" are not punctuation
+        let ends_word = is_letter(before) && after.is_none_or(|c| c.is_whitespace());
+
+        let label = match token.value {
+            // "end." or "first, then"
+            TokenValue::Output if ends_word => "this prints the current cell",
+            TokenValue::Input if ends_word => "this reads a byte into the current cell",
+            // "left-most"
+            TokenValue::DecrementCell if is_letter(before) && is_letter(after) => {
+                "this decrements the current cell"
+            }
+            _ => continue,
+        };
+
+        let c = token.value.to_char().unwrap_or(' ');
+        warnings.push(
+            Diagnostic::new(
+                "W0005",
+                Severity::Warning,
+                &format!("'{}' in a comment is executed as a command", c),
+            )
+            .with_primary(token.span, label)
+            .with_note("brainfuck has no comment syntax, avoid command characters in comments"),
+        );
+    }
+}
+
+/// Warns about characters in comments that look like commands but are ignored
+fn lookalikes(source: &str, warnings: &mut Vec<Diagnostic>) {
+    let mut line = 1;
+    let mut column = 1;
+
+    for (offset, c) in source.char_indices() {
+        if let Some((_, command)) = LOOKALIKES.iter().find(|(lookalike, _)| *lookalike == c) {
+            let span = Span {
+                start: offset,
+                end: offset + c.len_utf8(),
+                line,
+                column,
+                file: 0,
+            };
+            warnings.push(
+                Diagnostic::new(
+                    "W0006",
+                    Severity::Warning,
+                    &format!("'{}' looks like the command '{}'", c, command),
+                )
+                .with_primary(span, "this character is ignored")
+                .with_note(&format!(
+                    "replace it with '{}' if it is meant as a command",
+                    command
+                )),
+            );
+        }
+
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+}
+
+/// Adds a label covering two tokens if they are on the same line, or a label for each token
+fn labelled(
+    diagnostic: Diagnostic,
+    first: Span,
+    second: Span,
+    message: &str,
+    second_message: &str,
+) -> Diagnostic {
+    if first.line == second.line {
+        let span = Span {
+            end: second.end,
+            ..first
+        };
+        return diagnostic.with_primary(span, message);
+    }
+    diagnostic
+        .with_primary(first, message)
+        .with_secondary(second, second_message)
+}
+
+fn is_inverse(a: &TokenValue, b: &TokenValue) -> bool {
+    matches!(
+        (a, b),
+        (TokenValue::IncrementCell, TokenValue::DecrementCell)
+            | (TokenValue::DecrementCell, TokenValue::IncrementCell)
+            | (TokenValue::MoveLeft, TokenValue::MoveRight)
+            | (TokenValue::MoveRight, TokenValue::MoveLeft)
+    )
+}
+
+fn is_closing(token: &Token) -> bool {
+    token.value == TokenValue::JumpBackwardIfNonZero
+}
+
+/// Returns the index of the ']' closing the loop opened at start
+fn matching_bracket(commands: &[&Token], start: usize) -> usize {
+    let mut depth = 0;
+    for (index, token) in commands.iter().enumerate().skip(start) {
+        match token.value {
+            TokenValue::JumpForwardIfZero => depth += 1,
+            TokenValue::JumpBackwardIfNonZero => {
+                depth -= 1;
+                if depth == 0 {
+                    return index;
+                }
+            }
+            _ => {}
+        }
+    }
+    commands.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex;
+
+    fn codes(source: &str) -> Vec<&'static str> {
+        lint(source, &lex(source), CellWidth::Eight)
+            .iter()
+            .map(|warning| warning.code)
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_program() {
+        let source = "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+        assert!(codes(source).is_empty());
+    }
+
+    #[test]
+    fn test_cancelling_sequences() {
+        assert_eq!(codes("+++-."), vec!["W0001"]);
+        assert_eq!(codes("+>< -+-+"), vec!["W0001", "W0001", "W0001"]);
+
+        let warnings = lint("+>\n<", &lex("+>\n<"), CellWidth::Eight);
+        assert_eq!(warnings[0].primary.as_ref().unwrap().span.line, 1);
+        assert_eq!(warnings[0].secondary[0].span.line, 2);
+    }
+
+    #[test]
+    fn test_dead_loops() {
+        assert_eq!(codes("[comment]+[-]"), vec!["W0002"]);
+
+        let warnings = lint("+[-][>+<]", &lex("+[-][>+<]"), CellWidth::Eight);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, "W0002");
+        assert_eq!(warnings[0].primary.as_ref().unwrap().span.start, 4);
+        assert_eq!(warnings[0].secondary[0].span.start, 3);
+    }
+
+    #[test]
+    fn test_pointer_below_cell_zero() {
+        assert_eq!(codes("+>+<<"), vec!["W0003"]);
+        assert_eq!(codes("+[-<]"), vec!["W0003"]);
+        // the loop might not be entered
+        assert!(codes(",[<]").is_empty());
+        // the pointer is unknown after a loop that moves it
+        assert!(codes("+>+[>]<<<").is_empty());
+    }
+
+    #[test]
+    fn test_infinite_loop() {
+        let warnings = lint(">+[]>.", &lex(">+[]>."), CellWidth::Eight);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, "W0004");
+        assert_eq!(warnings[0].secondary[0].span.start, 4);
+
+        assert_eq!(codes("+[-]+[]"), vec!["W0004"]);
+        assert!(codes(",[]").is_empty());
+
+        // 300 fits into a 16-bit cell, but could have wrapped around in an 8-bit cell
+        let source = format!("{}[]", "+".repeat(300));
+        assert!(codes(&source).is_empty());
+        let warnings = lint(&source, &lex(&source), CellWidth::Sixteen);
+        assert_eq!(warnings[0].code, "W0004");
+    }
+
+    #[test]
+    fn test_comments() {
+        assert_eq!(codes("Print it. +."), vec!["W0005"]);
+        assert_eq!(codes("first, then > left-most ."), vec!["W0005", "W0005"]);
+        assert!(codes("Add 2 to cell #1 >++ . ,").is_empty());
+        assert!(codes("cell 3. 1,2 4-5").is_empty());
+        assert!(codes("x.y").is_empty());
+        assert_eq!(codes("x – y ＞"), vec!["W0006", "W0006"]);
+    }
+}
@@ -1,18 +1,188 @@
 use std::env;
+use std::fs;
 use std::process;
 
-use brainfuck_rust::{run, Config};
+use brainfuck_rust::{
+    assemble, check, check_bounds, compile, debug, find_config_file, fmt, generate, lint, minify,
+    profile, read_program, repl, run_with_final_cell, Command, Config, Diagnostic, Error,
+    ErrorFormat, ExitCode, USAGE,
+};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    let config: Config = Config::build(&args).unwrap_or_else(|e| {
+    let config_file_path = env::current_dir()
+        .ok()
+        .and_then(|dir| find_config_file(&dir));
+    let config = Config::with_config_file(&args, config_file_path.as_deref()).unwrap_or_else(|e| {
         eprintln!("Error: {}", e);
-        process::exit(1);
+        eprintln!("Run with --help to see the usage.");
+        process::exit(ExitCode::Usage.code());
     });
 
-    if let Err(e) = run(config) {
-        eprintln!("Error: {}", e);
-        process::exit(1);
+    let program_name = config.program_name().to_string();
+    let error_format = config.error_format;
+
+    // the source is read up front, so diagnostics can quote it even if it came from stdin
+    let mut source = None;
+    // the text of generate is not a program and is read as bytes
+    let reads_program = config.command.needs_program() && config.command != Command::Generate;
+    let result = if reads_program {
+        read_program(&config).and_then(|program_string| {
+            let result = execute(config, &program_string, &program_name);
+            source = Some(program_string);
+            result
+        })
+    } else {
+        execute(config, "", &program_name)
+    };
+
+    match result {
+        Ok(exit_code) => process::exit(exit_code),
+        Err(e) => {
+            let diagnostic = Diagnostic::from_error(&e);
+            match error_format {
+                ErrorFormat::Human => {
+                    let file = diagnostic
+                        .primary
+                        .as_ref()
+                        .map_or(0, |label| label.span.file);
+                    // labels in included files are shown in the file they point into
+                    let (file_name, source) = match (file, e.files().get(file)) {
+                        (0, _) => (program_name, source),
+                        (_, Some(file)) => {
+                            (file.path.display().to_string(), Some(file.source.clone()))
+                        }
+                        (_, None) => (program_name, None),
+                    };
+                    eprint!("{}", diagnostic.render(&file_name, source.as_deref()))
+                }
+                ErrorFormat::Json => eprintln!("{}", diagnostic.to_json(&program_name, e.files())),
+            }
+            process::exit(ExitCode::from_error(&e).code());
+        }
+    }
+}
+
+/// Executes the command selected on the command line and returns the exit code
+///
+/// # Arguments
+///
+/// * `config` - Config built from the command-line arguments
+/// * `source` - Source of the program, empty for commands that read no program
+/// * `program_name` - Name of the program in messages
+fn execute(config: Config, source: &str, program_name: &str) -> Result<i32, Error> {
+    match config.command {
+        Command::Run => {
+            let exit_code_from_cell = config.exit_code_from_cell;
+            let (_, cell) = run_with_final_cell(source, config)?;
+            if exit_code_from_cell {
+                // exit codes are limited to a byte
+                return Ok((cell & 0xff) as i32);
+            }
+        }
+        Command::Check => {
+            check(source, &config)?;
+            println!("{}: ok", program_name);
+        }
+        Command::Minify => {
+            let minified = minify(source, &config)?;
+            match &config.output_file_path {
+                Some(path) => fs::write(path, minified)?,
+                // a newline would become part of embedded input
+                None if config.embedded_input => print!("{}", minified),
+                None => println!("{}", minified),
+            }
+        }
+        Command::Lint => {
+            let warnings = lint(source, &config)?;
+            if !warnings.is_empty() {
+                return Ok(report_warnings(&warnings, source, &config, program_name));
+            }
+            println!("{}: ok", program_name);
+        }
+        Command::Bounds => {
+            let warnings = check_bounds(source, &config)?;
+            if !warnings.is_empty() {
+                return Ok(report_warnings(&warnings, source, &config, program_name));
+            }
+            println!("{}: the pointer stays on the tape", program_name);
+        }
+        Command::Generate => {
+            let program = generate(&config)?;
+            match &config.output_file_path {
+                Some(path) => fs::write(path, program)?,
+                None => println!("{}", program),
+            }
+        }
+        Command::Assemble => {
+            let program = assemble(source, &config)?;
+            match &config.output_file_path {
+                Some(path) => fs::write(path, program)?,
+                None => println!("{}", program),
+            }
+        }
+        Command::Compile => {
+            let c = compile(source, &config)?;
+            match &config.output_file_path {
+                Some(path) => fs::write(path, c)?,
+                None => print!("{}", c),
+            }
+        }
+        Command::Debug => {
+            debug(source, config)?;
+        }
+        Command::Profile => {
+            let report = profile(source, config)?;
+            eprint!("{}", report);
+        }
+        Command::Repl => repl(&config)?,
+        Command::Fmt => {
+            let formatted = fmt(source, &config)?;
+            let path = config.brainfuck_file_path.as_str();
+
+            if config.check_formatting {
+                if formatted != source {
+                    eprintln!("{}: not formatted", program_name);
+                    return Ok(ExitCode::Findings.code());
+                }
+            } else if path.is_empty() || path == "-" {
+                print!("{}", formatted);
+            } else if formatted != source {
+                fs::write(path, formatted)?;
+            }
+        }
+        Command::Help => println!("{}", USAGE),
+        Command::Version => println!("brainfuck_rust {}", env!("CARGO_PKG_VERSION")),
+    }
+
+    Ok(ExitCode::Success.code())
+}
+
+/// Prints warnings to stderr and returns the exit code for a program with warnings
+///
+/// # Arguments
+///
+/// * `warnings` - The warnings about the program
+/// * `source` - Source of the program the warnings point into
+/// * `config` - Config containing the error format
+/// * `program_name` - Name of the program shown in the warnings
+fn report_warnings(
+    warnings: &[Diagnostic],
+    source: &str,
+    config: &Config,
+    program_name: &str,
+) -> i32 {
+    for warning in warnings {
+        match config.error_format {
+            ErrorFormat::Human => eprintln!("{}", warning.render(program_name, Some(source))),
+            ErrorFormat::Json => eprintln!("{}", warning.to_json(program_name, &[])),
+        }
+    }
+
+    if config.error_format == ErrorFormat::Human {
+        let plural = if warnings.len() == 1 { "" } else { "s" };
+        eprintln!("{}: {} warning{}", program_name, warnings.len(), plural);
     }
+    ExitCode::Findings.code()
 }
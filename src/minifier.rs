@@ -0,0 +1,134 @@
+use crate::token::Token;
+
+/// Minifies a brainfuck program: comments are removed, adjacent inverse commands cancel each
+/// other, and loops that can never be entered are removed
+///
+/// A loop can't be entered directly after another loop, whose cell is zero when it ends, or at
+/// the start of the program, where every cell is zero. The minified program behaves like the
+/// original one as long as the original doesn't fail, e.g. `<>` on the first cell is removed
+/// although it moves the pointer below the tape.
+///
+/// # Arguments
+///
+/// * `tokens` - The token stream of a brainfuck program with balanced brackets
+pub fn minify(tokens: &[Token]) -> String {
+    let commands: Vec<char> = tokens
+        .iter()
+        .filter_map(|token| token.value.to_char())
+        .collect();
+    let mut minified: Vec<char> = Vec::with_capacity(commands.len());
+
+    let mut index = 0;
+    while index < commands.len() {
+        let command = commands[index];
+
+        if command == '[' && matches!(minified.last(), None | Some(']')) {
+            index = matching_bracket(&commands, index) + 1;
+            continue;
+        }
+
+        // the stack cancels nested pairs like `++--` as well
+        match minified.last() {
+            Some(&last) if is_inverse(last, command) => {
+                minified.pop();
+            }
+            _ => minified.push(command),
+        }
+        index += 1;
+    }
+
+    minified.into_iter().collect()
+}
+
+fn is_inverse(a: char, b: char) -> bool {
+    matches!((a, b), ('+', '-') | ('-', '+') | ('<', '>') | ('>', '<'))
+}
+
+/// Returns the index of the ']' closing the loop opened at start
+fn matching_bracket(commands: &[char], start: usize) -> usize {
+    let mut depth = 0;
+    for (index, &command) in commands.iter().enumerate().skip(start) {
+        match command {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return index;
+                }
+            }
+            _ => {}
+        }
+    }
+    commands.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::OverflowMode;
+    use crate::{lex, run, Config, EofPolicy};
+
+    fn minify_source(source: &str) -> String {
+        minify(&lex(source))
+    }
+
+    /// Runs a program with wrapping cells on the given input
+    fn run_with_input(source: &str, input: &str) -> Vec<u8> {
+        let config = Config {
+            inline_program: Some(format!("{}!{}", source, input)),
+            embedded_input: true,
+            overflow_mode: OverflowMode::Wrap,
+            eof_policy: EofPolicy::Zero,
+            ..Default::default()
+        };
+        run(config).unwrap()
+    }
+
+    #[test]
+    fn test_comments_are_removed() {
+        assert_eq!(minify_source("add two: ++ print: ."), "++.");
+    }
+
+    #[test]
+    fn test_inverse_commands_cancel() {
+        assert_eq!(minify_source("+-+"), "+");
+        assert_eq!(minify_source(">++--<."), ".");
+        assert_eq!(minify_source("+><-,"), ",");
+        assert_eq!(minify_source("+.-"), "+.-");
+    }
+
+    #[test]
+    fn test_dead_loops_are_removed() {
+        assert_eq!(minify_source("[->+<]+."), "+.");
+        assert_eq!(minify_source("+-[.]+."), "+.");
+        assert_eq!(minify_source("+[-][>+<][.[-]]."), "+[-].");
+        // the loop after `>` runs on another cell
+        assert_eq!(minify_source(",[.,]>[-]<."), ",[.,]>[-]<.");
+    }
+
+    #[test]
+    fn test_equivalence() {
+        let programs = [
+            ",[.,]",
+            "[comment loop: ,.] +++++[>+++++++++++++<-]>. <><> +- .",
+            ",>,<[->+<]>.[-][<]+-.",
+            "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.",
+            ",[>+<-]>[<+>-]<-+.><[.-]",
+        ];
+
+        for program in programs {
+            let minified = minify_source(program);
+            assert!(minified.len() <= program.len());
+            for input in ["", "a", "hello", "\u{1}\u{7f}"] {
+                assert_eq!(
+                    run_with_input(&minified, input),
+                    run_with_input(program, input),
+                    "{} on {:?}",
+                    program,
+                    input
+                );
+            }
+            assert_eq!(minify_source(&minified), minified);
+        }
+    }
+}
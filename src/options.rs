@@ -0,0 +1,248 @@
+use custom_error::custom_error;
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use crate::interpreter::EofPolicy;
+use crate::output::{FlushPolicy, Output};
+use crate::state::{BoundsMode, CellWidth, OverflowMode, State, DEFAULT_TAPE_LENGTH};
+use crate::CancellationToken;
+
+custom_error! { pub OptionsError
+    ZeroTapeLength = "the tape needs at least one cell",
+    RequiresGrowableTape = "a tape memory limit requires a growable tape",
+    TapeExceedsMemoryLimit{tape_bytes: usize, max_tape_bytes: usize} =
+        "the initial tape needs {tape_bytes} bytes, more than the memory limit of {max_tape_bytes} bytes"
+}
+
+/// Semantics, limits, and I/O of an interpreter
+///
+/// Options are built by chaining setters on `InterpreterOptions::new()` and checked when they are
+/// passed to `Interpreter::new`.
+pub struct InterpreterOptions {
+    pub(crate) tape_length: usize,
+    pub(crate) cell_width: CellWidth,
+    pub(crate) overflow_mode: OverflowMode,
+    pub(crate) bounds_mode: BoundsMode,
+    pub(crate) max_tape_bytes: Option<usize>,
+    pub(crate) eof_policy: EofPolicy,
+    pub(crate) max_steps: Option<u64>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) cancellation_token: Option<CancellationToken>,
+    /// Abort loops whose iterations repeat the same state, slows down the interpreter
+    pub(crate) detect_infinite_loops: bool,
+    /// Program input, stdin if None
+    pub(crate) reader: Option<Box<dyn Read + Send>>,
+    /// Program output, stdout if None
+    pub(crate) writer: Option<Box<dyn Write + Send>>,
+    pub(crate) flush_policy: FlushPolicy,
+    /// Receives one line per executed instruction if set
+    pub(crate) tracer: Option<Box<dyn Write + Send>>,
+    /// Count how often each token is executed
+    pub(crate) profile: bool,
+}
+
+impl Default for InterpreterOptions {
+    fn default() -> InterpreterOptions {
+        InterpreterOptions {
+            tape_length: DEFAULT_TAPE_LENGTH,
+            cell_width: CellWidth::default(),
+            overflow_mode: OverflowMode::default(),
+            bounds_mode: BoundsMode::default(),
+            max_tape_bytes: None,
+            eof_policy: EofPolicy::default(),
+            max_steps: None,
+            timeout: None,
+            cancellation_token: None,
+            detect_infinite_loops: false,
+            reader: None,
+            writer: None,
+            flush_policy: FlushPolicy::default(),
+            tracer: None,
+            profile: false,
+        }
+    }
+}
+
+impl InterpreterOptions {
+    /// Creates the options of the classic interpreter: 30000 8-bit cells that must not overflow
+    pub fn new() -> InterpreterOptions {
+        InterpreterOptions::default()
+    }
+
+    pub fn tape_length(mut self, tape_length: usize) -> InterpreterOptions {
+        self.tape_length = tape_length;
+        self
+    }
+
+    pub fn cell_width(mut self, cell_width: CellWidth) -> InterpreterOptions {
+        self.cell_width = cell_width;
+        self
+    }
+
+    pub fn overflow_mode(mut self, overflow_mode: OverflowMode) -> InterpreterOptions {
+        self.overflow_mode = overflow_mode;
+        self
+    }
+
+    pub fn bounds_mode(mut self, bounds_mode: BoundsMode) -> InterpreterOptions {
+        self.bounds_mode = bounds_mode;
+        self
+    }
+
+    /// Limits the memory of a growable tape
+    pub fn max_tape_bytes(mut self, max_tape_bytes: usize) -> InterpreterOptions {
+        self.max_tape_bytes = Some(max_tape_bytes);
+        self
+    }
+
+    pub fn eof_policy(mut self, eof_policy: EofPolicy) -> InterpreterOptions {
+        self.eof_policy = eof_policy;
+        self
+    }
+
+    pub fn max_steps(mut self, max_steps: u64) -> InterpreterOptions {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> InterpreterOptions {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn cancellation_token(
+        mut self,
+        cancellation_token: CancellationToken,
+    ) -> InterpreterOptions {
+        self.cancellation_token = Some(cancellation_token);
+        self
+    }
+
+    pub fn detect_infinite_loops(mut self, detect_infinite_loops: bool) -> InterpreterOptions {
+        self.detect_infinite_loops = detect_infinite_loops;
+        self
+    }
+
+    pub fn reader(mut self, reader: impl Read + Send + 'static) -> InterpreterOptions {
+        self.reader = Some(Box::new(reader));
+        self
+    }
+
+    pub fn writer(mut self, writer: impl Write + Send + 'static) -> InterpreterOptions {
+        self.writer = Some(Box::new(writer));
+        self
+    }
+
+    /// Traces every executed instruction to the tracer, used by the debug command
+    pub(crate) fn tracer(mut self, tracer: impl Write + Send + 'static) -> InterpreterOptions {
+        self.tracer = Some(Box::new(tracer));
+        self
+    }
+
+    /// Counts how often each token is executed, used by the profile command
+    pub(crate) fn profile(mut self, profile: bool) -> InterpreterOptions {
+        self.profile = profile;
+        self
+    }
+
+    pub fn flush_policy(mut self, flush_policy: FlushPolicy) -> InterpreterOptions {
+        self.flush_policy = flush_policy;
+        self
+    }
+
+    /// Checks that the options can be combined
+    pub fn validate(&self) -> Result<(), OptionsError> {
+        if self.tape_length == 0 {
+            return Err(OptionsError::ZeroTapeLength);
+        }
+
+        if let Some(max_tape_bytes) = self.max_tape_bytes {
+            if self.bounds_mode != BoundsMode::Grow {
+                return Err(OptionsError::RequiresGrowableTape);
+            }
+
+            let tape_bytes = self.tape_length.saturating_mul(self.cell_width.bytes());
+            if tape_bytes > max_tape_bytes {
+                return Err(OptionsError::TapeExceedsMemoryLimit {
+                    tape_bytes,
+                    max_tape_bytes,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates the initial state described by the options
+    pub(crate) fn build_state(&self) -> State {
+        let state = match self.bounds_mode {
+            BoundsMode::Error => State::with_tape_length(self.tape_length),
+            BoundsMode::Grow => State::growable(self.tape_length, self.max_tape_bytes),
+        };
+        let mut state = state.with_cell_width(self.cell_width);
+        state.overflow_mode = self.overflow_mode;
+        state
+    }
+
+    /// Takes the reader and the writer, falling back to stdin and stdout
+    pub(crate) fn take_io(&mut self) -> (Box<dyn Read + Send>, Output) {
+        let reader = self.reader.take().unwrap_or_else(|| Box::new(io::stdin()));
+        let writer = match self.writer.take() {
+            Some(writer) => Output::new(writer, self.flush_policy),
+            None => Output::stdout(self.flush_policy),
+        };
+        (reader, writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(InterpreterOptions::new().validate().is_ok());
+        assert!(InterpreterOptions::new()
+            .bounds_mode(BoundsMode::Grow)
+            .tape_length(16)
+            .cell_width(CellWidth::ThirtyTwo)
+            .max_tape_bytes(64)
+            .validate()
+            .is_ok());
+
+        assert!(matches!(
+            InterpreterOptions::new().tape_length(0).validate(),
+            Err(OptionsError::ZeroTapeLength)
+        ));
+        assert!(matches!(
+            InterpreterOptions::new().max_tape_bytes(1024).validate(),
+            Err(OptionsError::RequiresGrowableTape)
+        ));
+        assert!(matches!(
+            InterpreterOptions::new()
+                .bounds_mode(BoundsMode::Grow)
+                .tape_length(16)
+                .cell_width(CellWidth::ThirtyTwo)
+                .max_tape_bytes(32)
+                .validate(),
+            Err(OptionsError::TapeExceedsMemoryLimit {
+                tape_bytes: 64,
+                max_tape_bytes: 32
+            })
+        ));
+    }
+
+    #[test]
+    fn test_build_state() {
+        let state = InterpreterOptions::new()
+            .tape_length(8)
+            .cell_width(CellWidth::Sixteen)
+            .overflow_mode(OverflowMode::Wrap)
+            .build_state();
+
+        assert_eq!(state.tape_length(), 8);
+        assert_eq!(state.cell_width(), CellWidth::Sixteen);
+        assert_eq!(state.overflow_mode, OverflowMode::Wrap);
+        assert_eq!(state.bounds_mode, BoundsMode::Error);
+    }
+}
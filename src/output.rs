@@ -0,0 +1,154 @@
+use std::io::{self, BufWriter, Write};
+
+/// Determines when buffered program output is flushed to the underlying writer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FlushPolicy {
+    /// Flush after every newline and before every `,` input (suited for interactive programs)
+    #[default]
+    Newline,
+    /// Flush only before every `,` input, or when the buffer is full
+    Input,
+    /// Flush only when the program exits, or when the buffer is full (fastest for bulk output)
+    Exit,
+    /// Flush after every byte
+    Unbuffered,
+}
+
+/// Buffered writer for program output that flushes according to a FlushPolicy
+pub struct Output {
+    writer: BufWriter<Box<dyn Write + Send>>,
+    flush_policy: FlushPolicy,
+}
+
+impl Output {
+    pub fn new(writer: Box<dyn Write + Send>, flush_policy: FlushPolicy) -> Output {
+        Output {
+            writer: BufWriter::new(writer),
+            flush_policy,
+        }
+    }
+
+    pub fn stdout(flush_policy: FlushPolicy) -> Output {
+        Output::new(stdout_writer(), flush_policy)
+    }
+
+    /// Writes a single byte and flushes if the flush policy requires it
+    pub fn write_byte(&mut self, byte: u8) -> io::Result<()> {
+        self.writer.write_all(&[byte])?;
+
+        match self.flush_policy {
+            FlushPolicy::Unbuffered => self.writer.flush(),
+            FlushPolicy::Newline if byte == b'\n' => self.writer.flush(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Flushes pending output before the program reads input, so prompts are visible
+    pub fn before_input(&mut self) -> io::Result<()> {
+        match self.flush_policy {
+            FlushPolicy::Exit => Ok(()),
+            _ => self.writer.flush(),
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Returns an unbuffered writer to stdout, so that only the flush policy decides when output is
+/// written, the stdout of the standard library would flush at every newline
+#[cfg(unix)]
+fn stdout_writer() -> Box<dyn Write + Send> {
+    use std::fs::File;
+    use std::os::fd::AsFd;
+
+    match io::stdout().as_fd().try_clone_to_owned() {
+        Ok(descriptor) => Box::new(File::from(descriptor)),
+        Err(_) => Box::new(io::stdout()),
+    }
+}
+
+/// Returns stdout, which flushes at every newline regardless of the flush policy
+#[cfg(not(unix))]
+fn stdout_writer() -> Box<dyn Write + Send> {
+    Box::new(io::stdout())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Writer that only makes bytes visible once they are flushed
+    #[derive(Clone, Default)]
+    struct FlushRecorder {
+        pending: Arc<Mutex<Vec<u8>>>,
+        flushed: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl Write for FlushRecorder {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.pending.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            let mut pending = self.pending.lock().unwrap();
+            self.flushed.lock().unwrap().append(&mut pending);
+            Ok(())
+        }
+    }
+
+    fn write_all(output: &mut Output, bytes: &[u8]) {
+        for byte in bytes {
+            output.write_byte(*byte).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_flush_on_newline() {
+        let recorder = FlushRecorder::default();
+        let mut output = Output::new(Box::new(recorder.clone()), FlushPolicy::Newline);
+
+        write_all(&mut output, b"ab\ncd");
+        assert_eq!(*recorder.flushed.lock().unwrap(), b"ab\n");
+
+        output.before_input().unwrap();
+        assert_eq!(*recorder.flushed.lock().unwrap(), b"ab\ncd");
+    }
+
+    #[test]
+    fn test_flush_before_input() {
+        let recorder = FlushRecorder::default();
+        let mut output = Output::new(Box::new(recorder.clone()), FlushPolicy::Input);
+
+        write_all(&mut output, b"name?\n");
+        assert!(recorder.flushed.lock().unwrap().is_empty());
+
+        output.before_input().unwrap();
+        assert_eq!(*recorder.flushed.lock().unwrap(), b"name?\n");
+    }
+
+    #[test]
+    fn test_flush_at_exit() {
+        let recorder = FlushRecorder::default();
+        let mut output = Output::new(Box::new(recorder.clone()), FlushPolicy::Exit);
+
+        write_all(&mut output, b"a\nb\n");
+        output.before_input().unwrap();
+        assert!(recorder.flushed.lock().unwrap().is_empty());
+
+        output.flush().unwrap();
+        assert_eq!(*recorder.flushed.lock().unwrap(), b"a\nb\n");
+    }
+
+    #[test]
+    fn test_unbuffered() {
+        let recorder = FlushRecorder::default();
+        let mut output = Output::new(Box::new(recorder.clone()), FlushPolicy::Unbuffered);
+
+        write_all(&mut output, b"ab");
+        assert_eq!(*recorder.flushed.lock().unwrap(), b"ab");
+    }
+}
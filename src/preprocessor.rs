@@ -0,0 +1,351 @@
+use custom_error::custom_error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::lex;
+use crate::token::{Span, Token};
+
+/// Directive that includes the tokens of another file, e.g. `#include "divmod.bf"`
+const DIRECTIVE: &str = "#include";
+
+custom_error! { pub IncludeError
+    MissingPath{span: Span} =
+        @{ format!("expected a quoted path after #include at line {}, column {}", span.line, span.column) },
+    Unreadable{path: String, span: Span, source: io::Error} =
+        @{ format!("cannot include '{}' at line {}, column {}: {}", path, span.line, span.column, source) },
+    Cycle{path: String, span: Span} =
+        @{ format!("'{}' includes itself at line {}, column {}", path, span.line, span.column) },
+    TrailingCommands{span: Span} =
+        @{ format!("commands after #include at line {}, column {}", span.line, span.column) }
+}
+
+impl IncludeError {
+    /// Returns the location of the directive that failed
+    pub fn span(&self) -> Span {
+        match self {
+            IncludeError::MissingPath { span }
+            | IncludeError::Unreadable { span, .. }
+            | IncludeError::Cycle { span, .. }
+            | IncludeError::TrailingCommands { span } => *span,
+        }
+    }
+}
+
+/// A file of a program and its source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    /// Path of the file, empty for a program that was not read from a file
+    pub path: PathBuf,
+    pub source: String,
+}
+
+/// An include directive in a source file
+struct Directive {
+    /// Byte offset of the line of the directive
+    offset: usize,
+    path: String,
+    /// Location of the quoted path
+    span: Span,
+}
+
+/// Lexes a program and the files it includes, the files are kept so that spans can be traced
+/// back to them
+#[derive(Default)]
+pub struct Preprocessor {
+    /// Files read so far, the file of a span is an index into it and the main file comes first
+    pub files: Vec<SourceFile>,
+    /// Canonical paths of the files, None for a program that was not read from a file
+    canonical_paths: Vec<Option<PathBuf>>,
+    /// Files whose directives are being resolved, innermost last
+    stack: Vec<usize>,
+}
+
+impl Preprocessor {
+    /// Lexes a program, replacing every `#include "path"` line with the tokens of the file at path
+    ///
+    /// Paths are relative to the directory of the including file. A file may be included several
+    /// times, but not by itself or a file it includes.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - A string containing the brainfuck program
+    /// * `path` - Path of the program, includes of a program without a file are relative to the
+    ///   working directory
+    pub fn lex(&mut self, source: &str, path: &Path) -> Result<Vec<Token>, IncludeError> {
+        self.files.push(SourceFile {
+            path: path.to_path_buf(),
+            source: source.to_string(),
+        });
+        self.canonical_paths.push(fs::canonicalize(path).ok());
+
+        let mut tokens = self.lex_file(0)?;
+        tokens.extend(lex(source).pop());
+        Ok(tokens)
+    }
+
+    /// Lexes a file that has been read and the files it includes, without the end token
+    fn lex_file(&mut self, file: usize) -> Result<Vec<Token>, IncludeError> {
+        let source = self.files[file].source.clone();
+        let directives = directives(&source, file)?;
+
+        let mut own_tokens = lex(&blank_lines(&source));
+        own_tokens.pop();
+        for token in &mut own_tokens {
+            token.span.file = file;
+        }
+
+        self.stack.push(file);
+        let mut own_tokens = own_tokens.into_iter().peekable();
+        let mut tokens = Vec::new();
+        for directive in directives {
+            while let Some(token) = own_tokens.next_if(|t| t.span.start < directive.offset) {
+                tokens.push(token);
+            }
+            tokens.extend(self.include(&directive, file)?);
+        }
+        tokens.extend(own_tokens);
+        self.stack.pop();
+
+        Ok(tokens)
+    }
+
+    /// Reads the file of a directive, unless it was read before, and lexes it
+    fn include(&mut self, directive: &Directive, from: usize) -> Result<Vec<Token>, IncludeError> {
+        let directory = self.files[from].path.parent().unwrap_or(Path::new(""));
+        let path = directory.join(&directive.path);
+        let unreadable = |source| IncludeError::Unreadable {
+            path: path.display().to_string(),
+            span: directive.span,
+            source,
+        };
+
+        let canonical_path = fs::canonicalize(&path).map_err(unreadable)?;
+        let included = self
+            .canonical_paths
+            .iter()
+            .position(|known| known.as_ref() == Some(&canonical_path));
+
+        let file = match included {
+            Some(file) if self.stack.contains(&file) => {
+                return Err(IncludeError::Cycle {
+                    path: path.display().to_string(),
+                    span: directive.span,
+                })
+            }
+            Some(file) => file,
+            None => {
+                let source = fs::read_to_string(&path).map_err(unreadable)?;
+                self.files.push(SourceFile { path, source });
+                self.canonical_paths.push(Some(canonical_path));
+                self.files.len() - 1
+            }
+        };
+        self.lex_file(file)
+    }
+}
+
+/// Returns whether a line is an include directive, possibly with an unterminated or empty path
+///
+/// Lines that start with `#include` but have no quoted path are ordinary comments.
+fn is_directive(line: &str) -> bool {
+    line.trim_start()
+        .strip_prefix(DIRECTIVE)
+        .is_some_and(|rest| rest.trim_start().starts_with('"'))
+}
+
+/// Finds the include directives of a source file
+fn directives(source: &str, file: usize) -> Result<Vec<Directive>, IncludeError> {
+    let mut directives = Vec::new();
+    let mut offset = 0;
+
+    for (index, line) in source.split_inclusive('\n').enumerate() {
+        if is_directive(line) {
+            let indent = line.len() - line.trim_start().len();
+            let rest = &line[indent + DIRECTIVE.len()..];
+            let path_start = line.len() - rest.trim_start().len();
+            let span = |start: usize, end: usize| Span {
+                start: offset + start,
+                end: offset + end,
+                line: index + 1,
+                column: line[..start].chars().count() + 1,
+                file,
+            };
+
+            let (path, trailing) = rest
+                .trim_start()
+                .strip_prefix('"')
+                .and_then(|quoted| quoted.split_once('"'))
+                .filter(|(path, _)| !path.is_empty())
+                .ok_or(IncludeError::MissingPath {
+                    span: span(indent, indent + DIRECTIVE.len()),
+                })?;
+
+            // the line is blanked, so commands after the path would be dropped silently
+            let is_command = |c| Token::build(c).is_some();
+            let trailing_start = line.len() - trailing.len();
+            if let (Some(first), Some(last)) =
+                (trailing.find(is_command), trailing.rfind(is_command))
+            {
+                return Err(IncludeError::TrailingCommands {
+                    span: span(trailing_start + first, trailing_start + last + 1),
+                });
+            }
+
+            directives.push(Directive {
+                offset,
+                path: path.to_string(),
+                span: span(path_start, path_start + path.len() + 2),
+            });
+        }
+        offset += line.len();
+    }
+
+    Ok(directives)
+}
+
+/// Replaces the include directives of a source with spaces, so that the commands in their paths
+/// are not lexed, while the offsets, lines, and columns of the other commands stay the same
+///
+/// Fails for a directive without a path or with commands after its path, which would be lost.
+///
+/// # Arguments
+///
+/// * `source` - A string containing the brainfuck program
+pub fn blank_directives(source: &str) -> Result<String, IncludeError> {
+    directives(source, 0)?;
+    Ok(blank_lines(source))
+}
+
+fn blank_lines(source: &str) -> String {
+    source
+        .split_inclusive('\n')
+        .map(|line| {
+            if is_directive(line) {
+                let text = line.trim_end_matches(['\r', '\n']);
+                " ".repeat(text.len()) + &line[text.len()..]
+            } else {
+                line.to_string()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::TokenValue;
+    use std::env;
+
+    /// Creates a directory with the given files and returns its path, the directory is unique
+    /// to the test and the process and has to be removed by the test
+    fn directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let directory =
+            env::temp_dir().join(format!("brainfuck_rust_{}_{}", name, std::process::id()));
+        for (path, source) in files {
+            let path = directory.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, source).unwrap();
+        }
+        directory
+    }
+
+    fn chars(tokens: &[Token]) -> String {
+        tokens.iter().filter_map(|t| t.value.to_char()).collect()
+    }
+
+    #[test]
+    fn test_include() {
+        let directory = directory(
+            "include",
+            &[
+                (
+                    "main.bf",
+                    "+\n#include \"lib/clear.bf\"\n>\n  #include \"lib/clear.bf\"\n.",
+                ),
+                (
+                    "lib/clear.bf",
+                    "clear the cell.bf\n#include \"minus.bf\"\n]",
+                ),
+                ("lib/minus.bf", "[-"),
+            ],
+        );
+        let main = directory.join("main.bf");
+        let source = fs::read_to_string(&main).unwrap();
+
+        let mut preprocessor = Preprocessor::default();
+        let tokens = preprocessor.lex(&source, &main).unwrap();
+        assert_eq!(chars(&tokens), "+.[-]>.[-].");
+        assert_eq!(tokens.last().unwrap().value, TokenValue::End);
+
+        // every file is read once, and spans point into the file the token came from
+        assert_eq!(preprocessor.files.len(), 3);
+        assert_eq!(preprocessor.files[2].path, directory.join("lib/minus.bf"));
+        let files: Vec<usize> = tokens.iter().map(|t| t.span.file).collect();
+        assert_eq!(files, vec![0, 1, 2, 2, 1, 0, 1, 2, 2, 1, 0, 0]);
+        assert_eq!((tokens[4].span.line, tokens[4].span.column), (3, 1));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_errors() {
+        let directory = directory(
+            "include_errors",
+            &[
+                ("a.bf", "+\n#include \"b.bf\""),
+                ("b.bf", "-\n #include \"a.bf\""),
+            ],
+        );
+        let path = directory.join("a.bf");
+        let source = fs::read_to_string(&path).unwrap();
+
+        let mut preprocessor = Preprocessor::default();
+        match preprocessor.lex(&source, &path) {
+            Err(IncludeError::Cycle { span, .. }) => {
+                assert_eq!((span.file, span.line, span.column), (1, 2, 11));
+            }
+            result => panic!("expected a cycle, got {:?}", result),
+        }
+        // the files read before the error are kept for diagnostics
+        assert_eq!(preprocessor.files.len(), 2);
+
+        let missing = Preprocessor::default().lex("#include \"missing.bf\"", &path);
+        assert!(matches!(missing, Err(IncludeError::Unreadable { .. })));
+        let unterminated = Preprocessor::default().lex("#include \"missing.bf", &path);
+        assert!(matches!(
+            unterminated,
+            Err(IncludeError::MissingPath { .. })
+        ));
+
+        let trailing = Preprocessor::default().lex("#include \"a.bf\" then +++.\n", &path);
+        match trailing {
+            Err(IncludeError::TrailingCommands { span }) => {
+                assert_eq!((span.line, span.column, span.end - span.start), (1, 22, 4));
+            }
+            result => panic!("expected trailing commands, got {:?}", result),
+        }
+        // other text after the path is a comment
+        let comment = Preprocessor::default().lex("#include \"missing.bf\" prints\n", &path);
+        assert!(matches!(comment, Err(IncludeError::Unreadable { .. })));
+
+        // a line without a quoted path is a comment
+        let comment = Preprocessor::default().lex("#include this section prints.", &path);
+        assert_eq!(chars(&comment.unwrap()), ".");
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_blank_directives() {
+        let source = "+\n #include \"a.bf\"\r\n#included.\n#include b.bf\n-";
+        assert_eq!(
+            blank_directives(source).unwrap(),
+            "+\n                \r\n#included.\n#include b.bf\n-"
+        );
+        assert!(matches!(
+            blank_directives("#include \"a.bf\" +"),
+            Err(IncludeError::TrailingCommands { .. })
+        ));
+    }
+}
@@ -0,0 +1,65 @@
+use crate::token::TokenValue;
+use crate::Token;
+
+/// Number of most frequently executed tokens listed in a profile report
+const HOTTEST_TOKENS: usize = 10;
+
+/// Formats the execution counts of a profiled run as a human readable report
+///
+/// # Arguments
+///
+/// * `tokens` - The token stream that was executed
+/// * `counts` - Number of executions per token index
+pub fn report(tokens: &[Token], counts: &[u64]) -> String {
+    let total: u64 = counts.iter().sum();
+    let mut report = format!("executed instructions: {}\n\n", total);
+
+    report.push_str("instruction       count\n");
+    for symbol in ['>', '<', '+', '-', '.', ',', '[', ']'] {
+        let count: u64 = tokens
+            .iter()
+            .zip(counts)
+            .filter(|(token, _)| token.value.to_char() == Some(symbol))
+            .map(|(_, count)| count)
+            .sum();
+        report.push_str(&format!("{:<11} {:>11}\n", symbol, count));
+    }
+
+    let mut hottest: Vec<(usize, u64)> = counts
+        .iter()
+        .copied()
+        .enumerate()
+        .filter(|(index, count)| *count > 0 && tokens[*index].value != TokenValue::End)
+        .collect();
+    hottest.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    report.push_str("\nhottest tokens:\n");
+    report.push_str("token  instruction       count\n");
+    for (index, count) in hottest.into_iter().take(HOTTEST_TOKENS) {
+        report.push_str(&format!(
+            "{:>5}  {:<11} {:>11}\n",
+            index,
+            tokens[index].value.to_char().unwrap_or(' '),
+            count
+        ));
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex;
+
+    #[test]
+    fn test_report() {
+        let tokens = lex("++[-]");
+        let report = report(&tokens, &[1, 1, 1, 2, 2, 0]);
+
+        assert!(report.starts_with("executed instructions: 7\n"));
+        assert!(report.contains("+                     2\n"));
+        assert!(report.contains("-                     2\n"));
+        assert!(report.contains("    3  -                     2\n"));
+    }
+}
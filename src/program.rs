@@ -0,0 +1,190 @@
+use std::path::{Path, PathBuf};
+
+use crate::check::check_brackets;
+use crate::preprocessor::{Preprocessor, SourceFile};
+use crate::state::CellWidth;
+use crate::token::{Span, Token};
+use crate::{compile_macros, lex, lex_with_input, Error};
+
+/// A lexed brainfuck program whose brackets are known to match
+#[derive(Debug, PartialEq, Eq)]
+pub struct Program {
+    tokens: Vec<Token>,
+    input: Option<Vec<u8>>,
+    files: Vec<SourceFile>,
+}
+
+impl Program {
+    /// Lexes a brainfuck program and checks its brackets
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - A string containing the brainfuck program
+    pub fn parse(source: &str) -> Result<Program, Error> {
+        Program::from_tokens(lex(source), None, source)
+    }
+
+    /// Lexes a brainfuck program whose input is embedded after the first '!' and checks its
+    /// brackets
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - A string containing the brainfuck program, optionally followed by '!' and
+    ///   the input
+    pub fn parse_with_input(source: &str) -> Result<Program, Error> {
+        let (tokens, input) = lex_with_input(source);
+        Program::from_tokens(tokens, input, source)
+    }
+
+    /// Lexes a brainfuck program, replacing every `#include "path"` line with the tokens of the
+    /// file at path, and checks its brackets
+    ///
+    /// Paths are relative to the directory of the including file, the spans of the tokens refer
+    /// to the files returned by `files`. Errors in a program that included files carry the files
+    /// read up to the error.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - A string containing the brainfuck program
+    /// * `path` - Path of the program, an empty path resolves includes in the working directory
+    pub fn parse_with_includes(source: &str, path: &Path) -> Result<Program, Error> {
+        let mut preprocessor = Preprocessor::default();
+        let tokens = preprocessor
+            .lex(source, path)
+            .map_err(Error::from)
+            .and_then(|tokens| {
+                check_brackets(&tokens)?;
+                Ok(tokens)
+            })
+            .map_err(|error| error.with_files(&preprocessor.files))?;
+        Ok(Program {
+            tokens,
+            input: None,
+            files: preprocessor.files,
+        })
+    }
+
+    /// Compiles a program in the macro language to brainfuck, lexes it, and checks its brackets
+    ///
+    /// The spans of the tokens point at the statements of the macro source they were compiled
+    /// from, so errors are reported in the macro source.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - A string containing the program in the macro language
+    /// * `cell_width` - Number of bits of the cells the program runs on
+    pub fn parse_macros(source: &str, cell_width: CellWidth) -> Result<Program, Error> {
+        let assembly = compile_macros(source, cell_width)?;
+        let mut tokens = lex(&assembly.code);
+
+        // the end token of the macro source, lexed as brainfuck, is at its end
+        let end = lex(source)
+            .last()
+            .map_or(Span::default(), |token| token.span);
+        // the code consists of commands only, so every character is a token besides the end token
+        for (token, span) in tokens
+            .iter_mut()
+            .zip(assembly.source_map.into_iter().chain([end]))
+        {
+            token.span = span;
+        }
+        Program::from_tokens(tokens, None, source)
+    }
+
+    fn from_tokens(
+        tokens: Vec<Token>,
+        input: Option<Vec<u8>>,
+        source: &str,
+    ) -> Result<Program, Error> {
+        check_brackets(&tokens)?;
+        let files = vec![SourceFile {
+            path: PathBuf::new(),
+            source: source.to_string(),
+        }];
+        Ok(Program {
+            tokens,
+            input,
+            files,
+        })
+    }
+
+    /// Returns the program with input that was embedded in its source
+    pub(crate) fn with_input(self, input: Option<&str>) -> Program {
+        Program {
+            input: input.map(|input| input.as_bytes().to_vec()),
+            ..self
+        }
+    }
+
+    /// Returns the tokens of the program, the last token is always the end token
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// Returns the input embedded in the source, if the program was parsed with input
+    pub fn embedded_input(&self) -> Option<&[u8]> {
+        self.input.as_deref()
+    }
+
+    /// Returns the files the tokens came from, the file of a span is an index into them and the
+    /// main file comes first
+    pub fn files(&self) -> &[SourceFile] {
+        &self.files
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::TokenValue;
+
+    #[test]
+    fn test_parse() {
+        let program = Program::parse("+[-] comment").unwrap();
+        assert_eq!(program.tokens().len(), 5);
+        assert_eq!(program.tokens()[4].value, TokenValue::End);
+        assert_eq!(program.embedded_input(), None);
+
+        assert!(matches!(Program::parse("+["), Err(Error::Syntax { .. })));
+    }
+
+    #[test]
+    fn test_parse_with_input() {
+        let program = Program::parse_with_input(",.!ab").unwrap();
+        assert_eq!(program.tokens().len(), 3);
+        assert_eq!(program.embedded_input(), Some(&b"ab"[..]));
+
+        let program = Program::parse_with_input(",.").unwrap();
+        assert_eq!(program.embedded_input(), None);
+    }
+
+    #[test]
+    fn test_parse_with_includes() {
+        let source = "#include \"tests/lib/newline.bf\"\n]";
+        let error = Program::parse_with_includes(source, Path::new("")).unwrap_err();
+        // the error carries the files its spans point into
+        assert!(matches!(error.root(), Error::Syntax { .. }));
+        assert_eq!(error.files().len(), 2);
+
+        let error = Program::parse_with_includes("]", Path::new("")).unwrap_err();
+        assert!(matches!(error, Error::Syntax { .. }));
+        assert!(error.files().is_empty());
+    }
+
+    #[test]
+    fn test_parse_macros() {
+        let program = Program::parse_macros("var a\n\nadd a, 1\nout a", CellWidth::Eight).unwrap();
+        let lines: Vec<usize> = program
+            .tokens()
+            .iter()
+            .map(|token| token.span.line)
+            .collect();
+        assert_eq!(lines, vec![3, 4, 4]);
+        assert_eq!(program.tokens()[2].value, TokenValue::End);
+
+        assert!(matches!(
+            Program::parse_macros("add a, 1", CellWidth::Eight),
+            Err(Error::Macro { .. })
+        ));
+    }
+}
@@ -0,0 +1,386 @@
+use std::fs;
+use std::io::{self, BufRead, Cursor, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::interpreter::Interpreter;
+use crate::{
+    build_state, interpreter_options, split_embedded_input, CancellationToken, Config, Error,
+    Program, State,
+};
+
+/// Maximum number of states kept for :undo
+const MAX_UNDO_HISTORY: usize = 100;
+
+/// Number of cells shown left and right of the pointer after every line
+const TAPE_WINDOW_RADIUS: usize = 5;
+
+const HELP: &str = "\
+Every line is executed on the same tape. Meta-commands:
+  :reset        Start over with an empty tape
+  :dump         Print all cells up to the last non-zero cell
+  :load <FILE>  Execute a brainfuck file on the current tape
+  :undo         Revert the tape to the state before the last change
+  :help         Print this help
+  :quit         Leave the REPL
+',' reads the input written after '!' on the same line, e.g. ',.!a'. Ctrl-C aborts the
+running line, its changes to the tape are kept and can be reverted with :undo.";
+
+/// Writer that collects the output of a line so it can be forwarded even if the line fails
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Interactive session that executes brainfuck lines on a persistent state
+pub struct Repl<'a> {
+    config: &'a Config,
+    state: State,
+    history: Vec<State>,
+    /// Cancels the running line, reset before every line
+    cancellation_token: CancellationToken,
+}
+
+impl<'a> Repl<'a> {
+    pub fn new(config: &'a Config) -> Repl<'a> {
+        Repl {
+            config,
+            state: build_state(config),
+            history: Vec::new(),
+            cancellation_token: CancellationToken::new(),
+        }
+    }
+
+    /// Reads lines from input and writes prompts, program output, and the tape to output until
+    /// the input ends or :quit is entered
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        writeln!(
+            output,
+            "brainfuck REPL, enter :help for a list of meta-commands"
+        )?;
+        write!(output, "bf> ")?;
+        output.flush()?;
+
+        for line in input.lines() {
+            let line = line?;
+            let line = line.trim();
+
+            if line == ":quit" {
+                return Ok(());
+            }
+            self.execute_line(line, &mut output)?;
+
+            write!(output, "bf> ")?;
+            output.flush()?;
+        }
+
+        writeln!(output)
+    }
+
+    /// Executes a single line of brainfuck code or a meta-command
+    fn execute_line<W: Write>(&mut self, line: &str, output: &mut W) -> io::Result<()> {
+        match line.split_once(' ').unwrap_or((line, "")) {
+            ("", _) => return Ok(()),
+            (":help", _) => return writeln!(output, "{}", HELP),
+            (":dump", _) => return writeln!(output, "{}", self.dump()),
+            (":reset", _) => {
+                self.save_state();
+                self.state = build_state(self.config);
+            }
+            (":undo", _) => match self.history.pop() {
+                Some(state) => self.state = state,
+                None => writeln!(output, "nothing to undo")?,
+            },
+            (":load", path) => match fs::read_to_string(path.trim()) {
+                Ok(program_string) => {
+                    // a file is read like by run, including its embedded input and includes
+                    let (code, input) =
+                        split_embedded_input(&program_string, self.config.embedded_input);
+                    let input = input.unwrap_or("");
+                    self.execute(code, input, Path::new(path.trim()), output)?
+                }
+                Err(e) => writeln!(output, "Error: {}", e)?,
+            },
+            (command, _) if command.starts_with(':') => writeln!(
+                output,
+                "unknown meta-command {}, enter :help for help",
+                command
+            )?,
+            _ => {
+                let (code, input) = line.split_once('!').unwrap_or((line, ""));
+                self.execute(code, input, Path::new(""), output)?
+            }
+        }
+
+        writeln!(output, "{}", self.tape_window())
+    }
+
+    /// Executes brainfuck code on the current state and forwards its output
+    ///
+    /// # Arguments
+    ///
+    /// * `program_string` - The brainfuck code
+    /// * `input` - Input read by ','
+    /// * `path` - Location of the code, includes are resolved relative to it
+    /// * `output` - Receives the output of the code and errors
+    fn execute<W: Write>(
+        &mut self,
+        program_string: &str,
+        input: &str,
+        path: &Path,
+        output: &mut W,
+    ) -> io::Result<()> {
+        let program = if self.config.includes {
+            Program::parse_with_includes(program_string, path)
+        } else {
+            Program::parse(program_string)
+        };
+        let program = match program {
+            Ok(program) => program,
+            Err(e) => return writeln!(output, "Error: {}", e),
+        };
+
+        self.save_state();
+        let state = std::mem::replace(&mut self.state, State::with_tape_length(1));
+
+        let buffer = SharedBuffer::default();
+        self.cancellation_token.reset();
+        let options = interpreter_options(self.config)
+            .reader(Cursor::new(input.as_bytes().to_vec()))
+            .writer(buffer.clone())
+            .cancellation_token(self.cancellation_token.clone());
+        let mut interpreter = Interpreter::with_state(program.tokens(), state, options);
+
+        let result = interpreter.interpret();
+        // the state is kept even if the line failed, :undo reverts it
+        self.state = interpreter.state;
+
+        let program_output = buffer.0.lock().unwrap();
+        output.write_all(&program_output)?;
+        if !program_output.is_empty() && !program_output.ends_with(b"\n") {
+            writeln!(output)?;
+        }
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => writeln!(output, "Error: {}", e),
+        }
+    }
+
+    fn save_state(&mut self) {
+        if self.history.len() == MAX_UNDO_HISTORY {
+            self.history.remove(0);
+        }
+        self.history.push(self.state.clone());
+    }
+
+    /// Formats the cells around the pointer with the pointer marked below its cell
+    fn tape_window(&self) -> String {
+        let start = self.state.pointer.saturating_sub(TAPE_WINDOW_RADIUS);
+        let end = (self.state.pointer + TAPE_WINDOW_RADIUS + 1).min(self.state.tape_length());
+        format_cells(&self.state, start, end)
+    }
+
+    /// Formats all cells up to the last non-zero cell or the pointer
+    fn dump(&self) -> String {
+        let last_non_zero =
+            (0..self.state.tape_length()).rposition(|index| self.state.cell(index) != 0);
+        let end = last_non_zero.unwrap_or(0).max(self.state.pointer) + 1;
+        format_cells(&self.state, 0, end)
+    }
+}
+
+fn format_cells(state: &State, start: usize, end: usize) -> String {
+    let mut indices = String::from("cell ");
+    let mut values = String::from("value");
+    let mut pointer = String::from("     ");
+
+    for index in start..end {
+        let width = state
+            .cell(index)
+            .to_string()
+            .len()
+            .max(index.to_string().len())
+            + 1;
+        indices.push_str(&format!("{:>width$}", index, width = width));
+        values.push_str(&format!("{:>width$}", state.cell(index), width = width));
+        let marker = if index == state.pointer { "^" } else { "" };
+        pointer.push_str(&format!("{:>width$}", marker, width = width));
+    }
+
+    format!("{}\n{}\n{}", indices, values, pointer.trim_end())
+}
+
+/// Starts a REPL on stdin and stdout
+///
+/// # Arguments
+///
+/// * `config` - Config containing the tape semantics and execution limits
+pub fn repl(config: &Config) -> Result<(), Error> {
+    let mut repl = Repl::new(config);
+    interrupt::cancel_on_interrupt(repl.cancellation_token.clone());
+    repl.run(io::stdin().lock(), io::stdout())?;
+    Ok(())
+}
+
+/// Ctrl-C handling, so that it aborts the running line instead of the REPL
+#[cfg(unix)]
+mod interrupt {
+    use std::sync::OnceLock;
+
+    use crate::CancellationToken;
+
+    const SIGINT: i32 = 2;
+
+    /// Token cancelled by the signal handler, which can't capture it
+    static TOKEN: OnceLock<CancellationToken> = OnceLock::new();
+
+    extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+    }
+
+    extern "C" fn cancel(_: i32) {
+        if let Some(token) = TOKEN.get() {
+            token.cancel();
+        }
+    }
+
+    /// Cancels the token on Ctrl-C instead of ending the process
+    pub fn cancel_on_interrupt(token: CancellationToken) {
+        if TOKEN.set(token).is_ok() {
+            // SAFETY: the handler only loads the token and stores to its atomic flag, both of
+            // which are async-signal-safe
+            unsafe {
+                signal(SIGINT, cancel);
+            }
+        }
+    }
+}
+
+/// Ctrl-C ends the REPL on platforms without signals
+#[cfg(not(unix))]
+mod interrupt {
+    use crate::CancellationToken;
+
+    pub fn cancel_on_interrupt(_: CancellationToken) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_session(config: &Config, input: &str) -> String {
+        let mut output = Vec::new();
+        Repl::new(config)
+            .run(input.as_bytes(), &mut output)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_persistent_tape() {
+        let config = Config::default();
+        let output = run_session(&config, "+++>++\n<[->+<]>\n");
+
+        assert!(output.contains("cell  0 1 2 3 4 5 6\nvalue 3 2 0 0 0 0 0\n        ^\n"));
+        assert!(output.contains("cell  0 1 2 3 4 5 6\nvalue 0 5 0 0 0 0 0\n        ^\n"));
+    }
+
+    #[test]
+    fn test_undo_and_reset() {
+        let config = Config::default();
+        let output = run_session(
+            &config,
+            "+++\n++\n:undo\n:dump\n:reset\n:dump\n:undo\n:undo\n:undo\n",
+        );
+
+        assert!(output.contains("cell  0\nvalue 3\n      ^\n"));
+        assert!(output.contains("cell  0\nvalue 0\n      ^\n"));
+        assert!(output.contains("nothing to undo"));
+    }
+
+    #[test]
+    fn test_output_and_errors() {
+        let config = Config::default();
+        let output = run_session(&config, "++++++[>++++++++<-]>+.\n<-\n[\n:bogus\n");
+
+        assert!(output.contains("1\n"));
+        assert!(output.contains("Error: cell underflow"));
+        assert!(output.contains("Error: unmatched '['"));
+        assert!(output.contains("unknown meta-command :bogus"));
+    }
+
+    #[test]
+    fn test_input_after_exclamation_mark() {
+        let config = Config::default();
+        let output = run_session(&config, ",.!a\n");
+        assert!(output.contains("a\n"));
+        assert!(output.contains("value 97 0"));
+    }
+
+    #[test]
+    fn test_limits_from_config() {
+        let config = Config {
+            detect_infinite_loops: true,
+            ..Default::default()
+        };
+        let output = run_session(&config, "+[]\n");
+        assert!(output.contains("Error: infinite loop detected"));
+
+        let config = Config {
+            max_steps: Some(100),
+            ..Default::default()
+        };
+        let output = run_session(&config, "+[>+<]\n+\n");
+        assert!(output.contains("Error: step limit exceeded"));
+        // the limit applies to every line on its own
+        assert!(output.contains("value 1 26"));
+    }
+
+    #[test]
+    fn test_cancel_line() {
+        let config = Config::default();
+        let mut repl = Repl::new(&config);
+        let cancellation_token = repl.cancellation_token.clone();
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            cancellation_token.cancel();
+        });
+
+        let mut output = Vec::new();
+        repl.run(&b"+[]\n+\n"[..], &mut output).unwrap();
+        handle.join().unwrap();
+
+        // only the running line is aborted, the next one runs on its tape
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("cancelled after"));
+        assert!(output.contains("value 2"));
+    }
+
+    #[test]
+    fn test_load_with_includes() {
+        let config = Config {
+            includes: true,
+            ..Default::default()
+        };
+        let output = run_session(&config, ":load tests/include.bf\n");
+        assert!(output.contains("A\n"));
+        assert!(!output.contains("Error"));
+    }
+
+    #[test]
+    fn test_quit() {
+        let config = Config::default();
+        let output = run_session(&config, ":quit\n+\n");
+        assert!(!output.contains("value 1"));
+    }
+}
@@ -2,25 +2,211 @@ use custom_error::custom_error;
 
 custom_error! { pub StateTransitionError
     PointerUnderflow = "pointer underflow (state.pointer < 0)",
-    PointerOverflow = "pointer overflow (state.pointer > 29999)",
+    PointerOverflow = "pointer overflow (state.pointer > last cell of the tape)",
     CellUnderflow = "cell underflow (state.cells[*] < 0)",
-    CellOverflow = "cell underflow (state.cells[*] > 255)",
+    CellOverflow = "cell overflow (state.cells[*] > maximum cell value)",
+    MemoryLimitExceeded{max_tape_bytes: usize} =
+        "memory limit exceeded (tape would grow beyond {max_tape_bytes} bytes)",
 }
 
-#[derive(Debug)]
+/// Number of cells of the classic fixed-size tape and the initial size of a growable tape
+pub const DEFAULT_TAPE_LENGTH: usize = 30000;
+
+/// Behaviour of the tape when the pointer moves right of its last cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BoundsMode {
+    /// Moving right of the last cell is a PointerOverflow
+    #[default]
+    Error,
+    /// The tape is extended with zeroed cells, up to an optional memory limit
+    Grow,
+}
+
+/// Number of bits of a single cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CellWidth {
+    #[default]
+    Eight,
+    Sixteen,
+    ThirtyTwo,
+}
+
+impl CellWidth {
+    pub fn max_value(&self) -> u32 {
+        match self {
+            CellWidth::Eight => u8::MAX as u32,
+            CellWidth::Sixteen => u16::MAX as u32,
+            CellWidth::ThirtyTwo => u32::MAX,
+        }
+    }
+
+    pub fn bytes(&self) -> usize {
+        match self {
+            CellWidth::Eight => 1,
+            CellWidth::Sixteen => 2,
+            CellWidth::ThirtyTwo => 4,
+        }
+    }
+}
+
+/// Behaviour of a cell when it is incremented above its maximum or decremented below 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowMode {
+    /// Leaving the value range is a CellOverflow or CellUnderflow
+    #[default]
+    Error,
+    /// The value wraps around (e.g. 255 + 1 = 0 for 8-bit cells)
+    Wrap,
+}
+
+/// Cells of the tape, each stored in as many bytes as its width needs
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Cells {
+    Eight(Vec<u8>),
+    Sixteen(Vec<u16>),
+    ThirtyTwo(Vec<u32>),
+}
+
+impl Cells {
+    fn new(cell_width: CellWidth, length: usize) -> Cells {
+        match cell_width {
+            CellWidth::Eight => Cells::Eight(vec![0; length]),
+            CellWidth::Sixteen => Cells::Sixteen(vec![0; length]),
+            CellWidth::ThirtyTwo => Cells::ThirtyTwo(vec![0; length]),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Cells::Eight(cells) => cells.len(),
+            Cells::Sixteen(cells) => cells.len(),
+            Cells::ThirtyTwo(cells) => cells.len(),
+        }
+    }
+
+    fn get(&self, index: usize) -> u32 {
+        match self {
+            Cells::Eight(cells) => cells[index] as u32,
+            Cells::Sixteen(cells) => cells[index] as u32,
+            Cells::ThirtyTwo(cells) => cells[index],
+        }
+    }
+
+    /// Sets a cell, the value is truncated to the width of the cells
+    fn set(&mut self, index: usize, value: u32) {
+        match self {
+            Cells::Eight(cells) => cells[index] = value as u8,
+            Cells::Sixteen(cells) => cells[index] = value as u16,
+            Cells::ThirtyTwo(cells) => cells[index] = value,
+        }
+    }
+
+    fn resize(&mut self, length: usize) {
+        match self {
+            Cells::Eight(cells) => cells.resize(length, 0),
+            Cells::Sixteen(cells) => cells.resize(length, 0),
+            Cells::ThirtyTwo(cells) => cells.resize(length, 0),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct State {
-    pub cells: Vec<u8>,
-    pub pointer: usize,
+    cells: Cells,
+    pub(crate) pointer: usize,
+    pub(crate) bounds_mode: BoundsMode,
+    pub(crate) max_tape_bytes: Option<usize>,
+    cell_width: CellWidth,
+    pub(crate) overflow_mode: OverflowMode,
+}
+
+impl Default for State {
+    fn default() -> State {
+        State::new()
+    }
 }
 
 impl State {
+    /// Creates a state with a fixed-size tape of DEFAULT_TAPE_LENGTH 8-bit cells
     pub fn new() -> State {
+        State::with_tape_length(DEFAULT_TAPE_LENGTH)
+    }
+
+    /// Creates a state with a fixed-size tape
+    ///
+    /// # Arguments
+    ///
+    /// * `tape_length` - Number of cells of the tape
+    pub fn with_tape_length(tape_length: usize) -> State {
         State {
-            cells: vec![0; 30000],
+            cells: Cells::new(CellWidth::default(), tape_length.max(1)),
             pointer: 0,
+            bounds_mode: BoundsMode::Error,
+            max_tape_bytes: None,
+            cell_width: CellWidth::default(),
+            overflow_mode: OverflowMode::default(),
+        }
+    }
+
+    /// Creates a state with a tape that grows on demand
+    ///
+    /// # Arguments
+    ///
+    /// * `tape_length` - Initial number of cells of the tape
+    /// * `max_tape_bytes` - Optional upper bound for the number of bytes allocated for the tape,
+    ///   a tape that already exceeds it cannot grow
+    pub fn growable(tape_length: usize, max_tape_bytes: Option<usize>) -> State {
+        State {
+            bounds_mode: BoundsMode::Grow,
+            max_tape_bytes,
+            ..State::with_tape_length(tape_length)
+        }
+    }
+
+    /// Returns the state with cells of the given width, the cells are cleared
+    ///
+    /// # Arguments
+    ///
+    /// * `cell_width` - Number of bits of a cell
+    pub fn with_cell_width(self, cell_width: CellWidth) -> State {
+        State {
+            cells: Cells::new(cell_width, self.cells.len()),
+            cell_width,
+            ..self
         }
     }
 
+    /// Returns the index of the cell the pointer is at
+    pub fn pointer(&self) -> usize {
+        self.pointer
+    }
+
+    pub fn bounds_mode(&self) -> BoundsMode {
+        self.bounds_mode
+    }
+
+    pub fn overflow_mode(&self) -> OverflowMode {
+        self.overflow_mode
+    }
+
+    pub fn cell_width(&self) -> CellWidth {
+        self.cell_width
+    }
+
+    /// Returns the number of cells of the tape
+    pub fn tape_length(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Returns the value of a cell
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the cell, it must be less than the tape length
+    pub fn cell(&self, index: usize) -> u32 {
+        self.cells.get(index)
+    }
+
     pub fn move_left(&mut self) -> Result<(), StateTransitionError> {
         match self.pointer {
             0 => Err(StateTransitionError::PointerUnderflow),
@@ -32,45 +218,77 @@ impl State {
     }
 
     pub fn move_right(&mut self) -> Result<(), StateTransitionError> {
-        match self.pointer {
-            29999 => Err(StateTransitionError::PointerOverflow),
-            _ => {
-                self.pointer += 1;
-                Ok(())
+        if self.pointer + 1 == self.cells.len() {
+            match self.bounds_mode {
+                BoundsMode::Error => return Err(StateTransitionError::PointerOverflow),
+                BoundsMode::Grow => self.grow()?,
             }
         }
+
+        self.pointer += 1;
+        Ok(())
+    }
+
+    /// Doubles the length of the tape, bounded by max_tape_bytes
+    fn grow(&mut self) -> Result<(), StateTransitionError> {
+        let mut length = self.cells.len() * 2;
+
+        if let Some(max_tape_bytes) = self.max_tape_bytes {
+            let max_length = max_tape_bytes / self.cell_width.bytes();
+            if self.cells.len() >= max_length {
+                return Err(StateTransitionError::MemoryLimitExceeded { max_tape_bytes });
+            }
+            length = length.min(max_length);
+        }
+
+        self.cells.resize(length);
+        Ok(())
     }
 
     pub fn increment_cell(&mut self) -> Result<(), StateTransitionError> {
-        match self.cells[self.pointer].checked_add(1) {
-            None => Err(StateTransitionError::CellOverflow),
-            Some(pointer_value) => {
-                self.cells[self.pointer] = pointer_value;
+        let value = self.cells.get(self.pointer);
+        if value < self.cell_width.max_value() {
+            self.cells.set(self.pointer, value + 1);
+            return Ok(());
+        }
+
+        match self.overflow_mode {
+            OverflowMode::Error => Err(StateTransitionError::CellOverflow),
+            OverflowMode::Wrap => {
+                self.cells.set(self.pointer, 0);
                 Ok(())
             }
         }
     }
 
     pub fn decrement_cell(&mut self) -> Result<(), StateTransitionError> {
-        match self.cells[self.pointer].checked_sub(1) {
-            None => Err(StateTransitionError::CellUnderflow),
-            Some(pointer_value) => {
-                self.cells[self.pointer] = pointer_value;
+        let value = self.cells.get(self.pointer);
+        if value > 0 {
+            self.cells.set(self.pointer, value - 1);
+            return Ok(());
+        }
+
+        match self.overflow_mode {
+            OverflowMode::Error => Err(StateTransitionError::CellUnderflow),
+            OverflowMode::Wrap => {
+                self.cells.set(self.pointer, self.cell_width.max_value());
                 Ok(())
             }
         }
     }
 
-    pub fn get_cell_value(&mut self) -> u8 {
-        self.cells[self.pointer]
+    pub fn get_cell_value(&self) -> u32 {
+        self.cells.get(self.pointer)
     }
 
-    pub fn set_cell_value(&mut self, value: u8) {
-        self.cells[self.pointer] = value;
+    /// Sets the current cell, the value is truncated to the cell width
+    pub fn set_cell_value(&mut self, value: u32) {
+        self.cells.set(self.pointer, value);
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
 mod tests {
     use super::*;
 
@@ -165,4 +383,92 @@ mod tests {
             ),
         }
     }
+
+    #[test]
+    fn test_growable_tape() {
+        let mut state = State::growable(DEFAULT_TAPE_LENGTH, None);
+
+        for _ in 0..40000 {
+            state.move_right().unwrap();
+        }
+        state.increment_cell().unwrap();
+
+        assert_eq!(state.pointer, 40000);
+        assert_eq!(state.get_cell_value(), 1);
+        assert!(state.tape_length() > 40000);
+    }
+
+    #[test]
+    fn test_growable_tape_memory_limit() {
+        let mut state = State::growable(DEFAULT_TAPE_LENGTH, Some(50000));
+        assert_eq!(state.tape_length(), 30000);
+
+        for _ in 0..49999 {
+            state.move_right().unwrap();
+        }
+        assert_eq!(state.tape_length(), 50000);
+
+        match state.move_right() {
+            Err(StateTransitionError::MemoryLimitExceeded { max_tape_bytes }) => {
+                assert_eq!(max_tape_bytes, 50000)
+            }
+            _ => panic!("growing the tape above 50000 bytes should cause a MemoryLimitExceeded"),
+        }
+        assert_eq!(state.pointer, 49999);
+    }
+
+    #[test]
+    fn test_growable_tape_memory_limit_wide_cells() {
+        let mut state = State::growable(10, Some(64)).with_cell_width(CellWidth::ThirtyTwo);
+
+        for _ in 0..15 {
+            state.move_right().unwrap();
+        }
+        assert_eq!(state.tape_length(), 16);
+
+        match state.move_right() {
+            Err(StateTransitionError::MemoryLimitExceeded { .. }) => {}
+            _ => panic!("16 cells of 32 bits should exhaust a memory limit of 64 bytes"),
+        }
+    }
+
+    #[test]
+    fn test_fixed_tape_length() {
+        let mut state = State::with_tape_length(3);
+
+        state.move_right().unwrap();
+        state.move_right().unwrap();
+
+        match state.move_right() {
+            Err(StateTransitionError::PointerOverflow) => {}
+            _ => panic!("moving right from the last cell should lead to an PointerOverflow"),
+        }
+    }
+
+    #[test]
+    fn test_wrapping_cells() {
+        let mut state = State::new();
+        state.overflow_mode = OverflowMode::Wrap;
+
+        state.decrement_cell().unwrap();
+        assert_eq!(state.get_cell_value(), 255);
+
+        state.increment_cell().unwrap();
+        assert_eq!(state.get_cell_value(), 0);
+    }
+
+    #[test]
+    fn test_wide_cells() {
+        let mut state = State::new().with_cell_width(CellWidth::Sixteen);
+
+        state.set_cell_value(255);
+        state.increment_cell().unwrap();
+        assert_eq!(state.get_cell_value(), 256);
+
+        state.set_cell_value(65535);
+        match state.increment_cell() {
+            Err(StateTransitionError::CellOverflow) => {}
+            _ => panic!("incrementing a 16-bit cell above 65535 should cause a CellOverflow"),
+        }
+    }
 }
@@ -13,6 +13,23 @@ pub enum TokenValue {
     End,
 }
 
+impl TokenValue {
+    /// Returns the brainfuck command character of the token value
+    pub fn to_char(&self) -> Option<char> {
+        match *self {
+            TokenValue::MoveRight => Some('>'),
+            TokenValue::MoveLeft => Some('<'),
+            TokenValue::IncrementCell => Some('+'),
+            TokenValue::DecrementCell => Some('-'),
+            TokenValue::Output => Some('.'),
+            TokenValue::Input => Some(','),
+            TokenValue::JumpForwardIfZero => Some('['),
+            TokenValue::JumpBackwardIfNonZero => Some(']'),
+            TokenValue::End => None,
+        }
+    }
+}
+
 impl fmt::Debug for TokenValue {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -35,47 +52,68 @@ impl fmt::Display for TokenValue {
     }
 }
 
+/// Location of a token in the program source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct Span {
+    /// Byte offset of the first character
+    pub start: usize,
+    /// Byte offset after the last character
+    pub end: usize,
+    /// Line number, starting at 1
+    pub line: usize,
+    /// Column in characters, starting at 1
+    pub column: usize,
+    /// Index of the file the token came from in the files of its program, 0 for the main file
+    pub file: usize,
+}
+
 #[derive(PartialEq, Eq)]
 pub struct Token {
-    pub value: TokenValue,
+    pub(crate) value: TokenValue,
+    pub(crate) span: Span,
 }
 
 impl Token {
+    pub fn value(&self) -> &TokenValue {
+        &self.value
+    }
+
+    /// Returns the location of the token in the program source
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
     pub fn build(c: char) -> Option<Token> {
-        match c {
-            '>' => Some(Token {
-                value: TokenValue::MoveRight,
-            }),
-            '<' => Some(Token {
-                value: TokenValue::MoveLeft,
-            }),
-            '+' => Some(Token {
-                value: TokenValue::IncrementCell,
-            }),
-            '-' => Some(Token {
-                value: TokenValue::DecrementCell,
-            }),
-            '.' => Some(Token {
-                value: TokenValue::Output,
-            }),
-            ',' => Some(Token {
-                value: TokenValue::Input,
-            }),
-            '[' => Some(Token {
-                value: TokenValue::JumpForwardIfZero,
-            }),
-            ']' => Some(Token {
-                value: TokenValue::JumpBackwardIfNonZero,
-            }),
-            _ => None,
-        }
+        let value = match c {
+            '>' => TokenValue::MoveRight,
+            '<' => TokenValue::MoveLeft,
+            '+' => TokenValue::IncrementCell,
+            '-' => TokenValue::DecrementCell,
+            '.' => TokenValue::Output,
+            ',' => TokenValue::Input,
+            '[' => TokenValue::JumpForwardIfZero,
+            ']' => TokenValue::JumpBackwardIfNonZero,
+            _ => return None,
+        };
+
+        Some(Token {
+            value,
+            span: Span::default(),
+        })
     }
 
     pub fn build_end() -> Token {
         Token {
             value: TokenValue::End,
+            span: Span::default(),
         }
     }
+
+    /// Returns the token located at span
+    pub fn with_span(self, span: Span) -> Token {
+        Token { span, ..self }
+    }
 }
 
 impl fmt::Debug for Token {
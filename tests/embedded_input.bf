@@ -0,0 +1,4 @@
+Copies its input to the output until the end of the input is reached
+the input follows the exclamation mark at the end of this file
+
+,[.,]!Brainfuck
@@ -0,0 +1,3 @@
+Print the letter A and a newline with the newline library
+++++++++[>++++++++<-]>+.
+#include "lib/newline.bf"
@@ -1,12 +1,22 @@
-use brainfuck_rust::{run, Config};
+// the original tests assert constants to mark the expected and unexpected branches
+#![allow(clippy::assertions_on_constants)]
+
+use brainfuck_rust::{
+    assemble, check_bounds, decode_output, fmt, generate, read_program, run, Config, EofPolicy,
+    Error, FlushPolicy, Interpreter, InterpreterError, InterpreterOptions, OptionsError,
+    OverflowMode, Program, TokenValue,
+};
+use std::io::{self, Cursor};
+use std::path::Path;
 
 #[test]
 fn test_hello_world() {
     let config = Config {
         brainfuck_file_path: String::from("tests/hello_world.bf"),
+        ..Default::default()
     };
     match run(config) {
-        Ok(s) => assert_eq!(String::from("Hello World!\n"), s),
+        Ok(output) => assert_eq!(String::from("Hello World!\n"), decode_output(&output)),
         _ => assert!(
             false,
             "Expected \"Hello World!\\n\" as a return value from brainfuck::run"
@@ -14,3 +24,182 @@ fn test_hello_world() {
     }
     assert!(true);
 }
+
+#[test]
+fn test_hello_world_step_limit() {
+    let config = Config {
+        brainfuck_file_path: String::from("tests/hello_world.bf"),
+        max_steps: Some(100),
+        ..Default::default()
+    };
+    match run(config) {
+        Err(e) => assert!(matches!(
+            e.root(),
+            Error::Interpreter {
+                source: InterpreterError::StepLimitExceeded { steps: 100, .. }
+            }
+        )),
+        Ok(_) => panic!("hello_world.bf needs more than 100 steps"),
+    }
+}
+
+#[test]
+fn test_embedded_input() {
+    let config = Config {
+        brainfuck_file_path: String::from("tests/embedded_input.bf"),
+        embedded_input: true,
+        eof_policy: EofPolicy::Zero,
+        ..Default::default()
+    };
+    assert_eq!(run(config).unwrap(), b"Brainfuck\n");
+}
+
+#[test]
+fn test_fmt() {
+    let config = Config {
+        brainfuck_file_path: String::from("tests/hello_world.bf"),
+        format_width: 40,
+        ..Default::default()
+    };
+    let source = read_program(&config).unwrap();
+    let formatted = fmt(&source, &config).unwrap();
+    // comments are not wrapped
+    let mut code_lines = formatted
+        .lines()
+        .filter(|line| line.trim().chars().all(|c| "+-<>[].,".contains(c)));
+    assert!(code_lines.all(|line| line.len() <= 40));
+
+    assert_eq!(fmt(&formatted, &config).unwrap(), formatted);
+    let config = Config {
+        inline_program: Some(formatted),
+        ..Default::default()
+    };
+    assert_eq!(run(config).unwrap(), b"Hello World!\n");
+}
+
+#[test]
+fn test_check_bounds() {
+    let config = Config {
+        tape_size: 3,
+        ..Default::default()
+    };
+    assert!(check_bounds("++[>+++[>+<-]<-]>>.", &config)
+        .unwrap()
+        .is_empty());
+
+    let warnings = check_bounds("++[>+++[>+<-]<-]>>>.", &config).unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].primary.as_ref().unwrap().span.column, 19);
+}
+
+#[test]
+fn test_generate() {
+    let config = Config {
+        brainfuck_file_path: String::from("tests/hello_world.bf"),
+        ..Default::default()
+    };
+    let program = generate(&config).unwrap();
+
+    let config = Config {
+        inline_program: Some(program),
+        ..Default::default()
+    };
+    let text = std::fs::read("tests/hello_world.bf").unwrap();
+    assert_eq!(run(config).unwrap(), text);
+}
+
+#[test]
+fn test_macros() {
+    let source = "
+        var count, digit
+        macro countdown from {
+            while from { out digit sub digit, 1 sub from, 1 }
+        }
+        set count, 3
+        set digit, 51
+        countdown count
+        print \"!\"
+    ";
+    let config = Config {
+        inline_program: Some(String::from(source)),
+        macros: true,
+        ..Default::default()
+    };
+    assert_eq!(run(config).unwrap(), b"321!");
+
+    let code = assemble("var a\nsub a, 1", &Config::default()).unwrap();
+    assert_eq!(code, "-");
+
+    // runtime errors point at the statement in the macro source
+    let config = Config {
+        inline_program: Some(String::from("var a\nsub a, 1")),
+        macros: true,
+        ..Default::default()
+    };
+    let error = run(config).unwrap_err();
+    match error {
+        Error::Runtime { source } => assert_eq!(source.span.line, 2),
+        error => panic!("unexpected error {}", error),
+    }
+}
+
+#[test]
+fn test_include() {
+    let config = Config {
+        brainfuck_file_path: String::from("tests/include.bf"),
+        includes: true,
+        ..Default::default()
+    };
+    assert_eq!(run(config).unwrap(), b"A\n");
+
+    // without --includes the directive is a comment
+    let config = Config {
+        inline_program: Some(String::from("#include \"missing\" +++")),
+        ..Default::default()
+    };
+    assert!(run(config).is_ok());
+
+    // tokens remember the file they came from
+    let source = std::fs::read_to_string("tests/include.bf").unwrap();
+    let program = Program::parse_with_includes(&source, Path::new("tests/include.bf")).unwrap();
+    assert_eq!(program.files().len(), 2);
+    assert_eq!(program.files()[1].path, Path::new("tests/lib/newline.bf"));
+    let last_command = &program.tokens()[program.tokens().len() - 2];
+    let span = last_command.span();
+    assert_eq!((span.file, span.line), (1, 2));
+}
+
+#[test]
+fn test_public_api() {
+    let program = Program::parse_with_input(",[.,]!abc").unwrap();
+
+    let options = InterpreterOptions::new()
+        .tape_length(4)
+        .overflow_mode(OverflowMode::Wrap)
+        .eof_policy(EofPolicy::Zero)
+        .reader(Cursor::new(program.embedded_input().unwrap().to_vec()))
+        .writer(io::sink())
+        .flush_policy(FlushPolicy::Exit);
+    let mut interpreter = Interpreter::new(program.tokens(), options).unwrap();
+
+    assert_eq!(interpreter.interpret().unwrap(), b"abc");
+    assert_eq!(interpreter.state().tape_length(), 4);
+    assert!((0..4).all(|index| interpreter.state().cell(index) == 0));
+    assert_eq!(
+        *interpreter.tokens().last().unwrap().value(),
+        TokenValue::End
+    );
+}
+
+#[test]
+fn test_invalid_options() {
+    let program = Program::parse("+").unwrap();
+    let options = InterpreterOptions::new().max_tape_bytes(1024);
+
+    assert!(matches!(
+        Interpreter::new(program.tokens(), options),
+        Err(Error::Options {
+            source: OptionsError::RequiresGrowableTape
+        })
+    ));
+}
@@ -0,0 +1,2 @@
+Print a newline from the current cell and clear it
+[-]++++++++++.[-]